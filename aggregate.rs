@@ -0,0 +1,462 @@
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use sqlparser::ast::{Expr, Function, FunctionArg, FunctionArgExpr};
+
+use crate::error::QueryError;
+use crate::functions::{FunctionRegistry, Signature};
+use crate::value::{HashKey, Value};
+
+// The state of a user-defined aggregate while it runs over one group: `init` starts an empty group,
+// `update` adds the arguments of one row, `merge` adds everything another state of the same aggregate
+// has seen, and `finalize` gives the aggregate's value. Rows with NULL arguments are skipped unless the
+// signature says the aggregate is called on NULLs.
+pub trait Accumulator {
+    fn init() -> Self
+    where
+        Self: Sized;
+
+    fn update(&mut self, args: &[Value]) -> Result<(), QueryError>;
+
+    fn merge(&mut self, other: Self) -> Result<(), QueryError>
+    where
+        Self: Sized;
+
+    fn finalize(&self) -> Result<Value, QueryError>;
+}
+
+// An accumulator behind a Box, whatever its type: a state can only be merged with another state
+// of the same aggregate, which is always the case for the states a UserAggregate hands out
+pub trait State {
+    fn update(&mut self, args: &[Value]) -> Result<(), QueryError>;
+
+    fn merge(&mut self, other: Box<dyn State>) -> Result<(), QueryError>;
+
+    fn finalize(&self) -> Result<Value, QueryError>;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<A: Accumulator + 'static> State for A {
+    fn update(&mut self, args: &[Value]) -> Result<(), QueryError> {
+        Accumulator::update(self, args)
+    }
+
+    fn merge(&mut self, other: Box<dyn State>) -> Result<(), QueryError> {
+        let other = other.into_any().downcast::<A>().expect("merged states of different aggregates");
+        Accumulator::merge(self, *other)
+    }
+
+    fn finalize(&self) -> Result<Value, QueryError> {
+        Accumulator::finalize(self)
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+// An aggregate function registered by host code, e.g. MEDIAN
+#[derive(Clone)]
+pub struct UserAggregate {
+    pub name: String,
+    pub signature: Signature,
+    init: Rc<dyn Fn() -> Box<dyn State>>,
+}
+
+impl UserAggregate {
+    pub fn new<A: Accumulator + 'static>(name: &str, signature: Signature) -> Self {
+        UserAggregate { name: name.to_uppercase(), signature, init: Rc::new(|| Box::new(A::init())) }
+    }
+
+    pub fn accumulator(&self) -> Box<dyn State> {
+        (self.init)()
+    }
+}
+
+impl fmt::Debug for UserAggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserAggregate").field("name", &self.name).field("signature", &self.signature).finish()
+    }
+}
+
+// Aggregates are the same if they have the same name, since a registry holds one aggregate per name
+impl PartialEq for UserAggregate {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+// The aggregate functions the evaluator knows about
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    User(UserAggregate),
+}
+
+impl AggregateFunction {
+    // One of the built-in aggregates
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+
+    // A built-in aggregate, or one registered with the catalog's functions
+    pub fn resolve(name: &str, functions: &FunctionRegistry) -> Option<Self> {
+        AggregateFunction::from_name(name).or_else(|| functions.aggregate(name).cloned().map(AggregateFunction::User))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            AggregateFunction::Count => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+            AggregateFunction::User(user) => &user.name,
+        }
+    }
+}
+
+// One aggregate call, e.g. `COUNT(DISTINCT major)`; args is empty for `COUNT(*)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate<'a> {
+    pub function: AggregateFunction,
+    pub args: Vec<&'a Expr>,
+    pub distinct: bool,
+}
+
+// Whether the expression is a call to an aggregate function (window calls with OVER are not)
+pub fn is_aggregate(expr: &Expr, functions: &FunctionRegistry) -> bool {
+    match expr {
+        Expr::Function(function) => {
+            function.over.is_none() && AggregateFunction::resolve(&function.name.to_string(), functions).is_some()
+        }
+        _ => false,
+    }
+}
+
+impl<'a> Aggregate<'a> {
+    // Recognize an aggregate call and check its arguments; Ok(None) for any other function
+    pub fn parse(function: &'a Function, functions: &FunctionRegistry) -> Result<Option<Self>, QueryError> {
+        match AggregateFunction::resolve(&function.name.to_string(), functions) {
+            Some(aggregate) if function.over.is_none() => Aggregate::from_call(aggregate, function).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // Check the arguments of a call to a known aggregate, which may also be used as a window function
+    pub fn from_call(aggregate: AggregateFunction, function: &'a Function) -> Result<Self, QueryError> {
+        let invalid = |message: &str| QueryError::InvalidArguments {
+            function: aggregate.name().to_string(),
+            message: message.to_string(),
+        };
+        let mut args = Vec::new();
+        for arg in &function.args {
+            match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => args.push(expr),
+                FunctionArg::Unnamed(FunctionArgExpr::Wildcard)
+                    if aggregate == AggregateFunction::Count && function.args.len() == 1 =>
+                {
+                    if function.distinct {
+                        return Err(invalid("DISTINCT cannot be used with *"));
+                    }
+                }
+                FunctionArg::Unnamed(_) => return Err(invalid("* is only allowed in COUNT(*)")),
+                other => return Err(invalid(&format!("unexpected argument {}", other))),
+            }
+        }
+        match &aggregate {
+            AggregateFunction::User(user) => user.signature.check_arity(&user.name, args.len())?,
+            AggregateFunction::Count if args.is_empty() && !function.args.is_empty() => {}
+            _ if args.len() != 1 => return Err(invalid(&format!("expected 1 argument, found {}", function.args.len()))),
+            _ => {}
+        }
+        Ok(Aggregate { function: aggregate, args, distinct: function.distinct })
+    }
+
+    // Compute the aggregate over a group of rows; `arg` evaluates an argument against one row.
+    // NULL arguments are skipped, and everything except COUNT is NULL for a group with no values.
+    pub fn evaluate<F>(&self, rows: &[&[Value]], mut arg: F) -> Result<Value, QueryError>
+    where
+        F: FnMut(&Expr, &[Value]) -> Result<Value, QueryError>,
+    {
+        if let AggregateFunction::User(user) = &self.function {
+            let state = self.accumulate(user, rows, &mut HashSet::new(), &mut arg)?;
+            return user.signature.check_result(&user.name, state.finalize()?);
+        }
+        let expr = match self.args.first() {
+            Some(expr) => expr,
+            None => return Ok(Value::Integer(rows.len() as i64)),
+        };
+        let mut values = Vec::new();
+        let mut seen = HashSet::new();
+        for row in rows {
+            let value = arg(expr, row)?;
+            if value != Value::Null && (!self.distinct || seen.insert(value.key())) {
+                values.push(value);
+            }
+        }
+
+        match self.function {
+            AggregateFunction::Count => Ok(Value::Integer(values.len() as i64)),
+            AggregateFunction::Sum => self.sum(&values),
+            AggregateFunction::Avg if values.is_empty() => Ok(Value::Null),
+            // Added up as floats, so integers can have an average even when their SUM overflows
+            AggregateFunction::Avg => {
+                let mut total = 0.0;
+                for value in &values {
+                    total += value.as_f64().ok_or_else(|| self.not_a_number(value))?;
+                }
+                Ok(Value::Float(total / values.len() as f64))
+            }
+            AggregateFunction::Min => self.extreme(values, Ordering::Less),
+            AggregateFunction::Max => self.extreme(values, Ordering::Greater),
+            AggregateFunction::User(_) => unreachable!("user-defined aggregates are accumulated above"),
+        }
+    }
+
+    // An aggregate over frames that only grow at their end, see Running; None for the built-in aggregates
+    pub fn running(&self) -> Option<Running<'_, 'a>> {
+        match &self.function {
+            AggregateFunction::User(user) => {
+                Some(Running { aggregate: self, user, state: user.accumulator(), end: 0, seen: HashSet::new() })
+            }
+            _ => None,
+        }
+    }
+
+    // Run a user-defined aggregate over some rows: every row's arguments are converted to the parameter
+    // types and passed to a new accumulator. For DISTINCT, `seen` has the arguments already accumulated,
+    // which may be in other states this one gets merged with.
+    fn accumulate<F>(
+        &self,
+        user: &UserAggregate,
+        rows: &[&[Value]],
+        seen: &mut HashSet<Vec<HashKey>>,
+        arg: &mut F,
+    ) -> Result<Box<dyn State>, QueryError>
+    where
+        F: FnMut(&Expr, &[Value]) -> Result<Value, QueryError>,
+    {
+        let mut accumulator = user.accumulator();
+        for row in rows {
+            let mut values = Vec::new();
+            for expr in &self.args {
+                values.push(arg(expr, row)?);
+            }
+            if user.signature.strict && values.contains(&Value::Null) {
+                continue;
+            }
+            let values = user.signature.convert_args(&user.name, &values)?;
+            if self.distinct && !seen.insert(values.iter().map(Value::key).collect()) {
+                continue;
+            }
+            accumulator.update(&values)?;
+        }
+        Ok(accumulator)
+    }
+
+    // Integers add up to an integer, anything involving a float to a float
+    fn sum(&self, values: &[Value]) -> Result<Value, QueryError> {
+        let mut total: Option<Value> = None;
+        for value in values {
+            total = Some(match (total, value) {
+                (None, Value::Integer(_) | Value::Float(_)) => value.clone(),
+                (Some(Value::Integer(a)), Value::Integer(b)) => {
+                    a.checked_add(*b).map(Value::Integer).ok_or(QueryError::NumericOverflow { expr: None })?
+                }
+                (Some(sum), Value::Integer(_) | Value::Float(_)) => {
+                    Value::Float(sum.as_f64().unwrap_or_default() + value.as_f64().unwrap_or_default())
+                }
+                (_, other) => return Err(self.not_a_number(other)),
+            });
+        }
+        Ok(total.unwrap_or(Value::Null))
+    }
+
+    fn not_a_number(&self, value: &Value) -> QueryError {
+        QueryError::InvalidArguments {
+            function: self.function.name().to_string(),
+            message: format!("expected a number, found {}", value.describe()),
+        }
+    }
+
+    // The smallest (Less) or largest (Greater) value
+    fn extreme(&self, values: Vec<Value>, wanted: Ordering) -> Result<Value, QueryError> {
+        let mut best = Value::Null;
+        for value in values {
+            if best == Value::Null || value.compare(&best)? == Some(wanted) {
+                best = value;
+            }
+        }
+        Ok(best)
+    }
+}
+
+// A user-defined aggregate over frames that only grow at their end, like a window's default frame
+// `RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW`: the rows each frame adds are accumulated on
+// their own and merged into the state so far, rather than starting over from the first row.
+pub struct Running<'r, 'a> {
+    aggregate: &'r Aggregate<'a>,
+    user: &'r UserAggregate,
+    state: Box<dyn State>,
+    end: usize,
+    seen: HashSet<Vec<HashKey>>,
+}
+
+impl Running<'_, '_> {
+    // The aggregate over the first `end` rows
+    pub fn evaluate<F>(&mut self, rows: &[&[Value]], end: usize, mut arg: F) -> Result<Value, QueryError>
+    where
+        F: FnMut(&Expr, &[Value]) -> Result<Value, QueryError>,
+    {
+        if end < self.end {
+            self.state = self.user.accumulator();
+            self.end = 0;
+            self.seen.clear();
+        }
+        let added = self.aggregate.accumulate(self.user, &rows[self.end..end], &mut self.seen, &mut arg)?;
+        self.state.merge(added)?;
+        self.end = end;
+        self.user.signature.check_result(&self.user.name, self.state.finalize()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{ArgType, Returns};
+    use crate::value::DataType;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    fn call(sql: &str) -> Function {
+        match Parser::new(&GenericDialect {}).try_with_sql(sql).unwrap().parse_expr().unwrap() {
+            Expr::Function(function) => function,
+            other => panic!("not a function call: {}", other),
+        }
+    }
+
+    // The middle value, or the mean of the two middle values
+    struct Median(Vec<f64>);
+
+    impl Accumulator for Median {
+        fn init() -> Self {
+            Median(Vec::new())
+        }
+
+        fn update(&mut self, args: &[Value]) -> Result<(), QueryError> {
+            self.0.extend(args[0].as_f64());
+            Ok(())
+        }
+
+        fn merge(&mut self, other: Self) -> Result<(), QueryError> {
+            self.0.extend(other.0);
+            Ok(())
+        }
+
+        fn finalize(&self) -> Result<Value, QueryError> {
+            let mut values = self.0.clone();
+            values.sort_by(f64::total_cmp);
+            let middle = values.len() / 2;
+            Ok(match values.len() {
+                0 => Value::Null,
+                n if n % 2 == 0 => Value::Float((values[middle - 1] + values[middle]) / 2.0),
+                _ => Value::Float(values[middle]),
+            })
+        }
+    }
+
+    fn run(sql: &str, values: &[Value]) -> Result<Value, QueryError> {
+        let function = call(sql);
+        let mut functions = FunctionRegistry::default();
+        let signature = Signature::new(vec![ArgType::Numeric], Returns::Type(DataType::Float));
+        functions.register_aggregate::<Median>("median", signature).unwrap();
+        let aggregate = Aggregate::parse(&function, &functions)?.unwrap();
+        let rows: Vec<&[Value]> = values.iter().map(std::slice::from_ref).collect();
+        aggregate.evaluate(&rows, |_, row| Ok(row[0].clone()))
+    }
+
+    #[test]
+    fn test_aggregates_skip_nulls() {
+        let values = [Value::Integer(3), Value::Null, Value::Integer(1), Value::Integer(3)];
+        assert_eq!(run("COUNT(*)", &values), Ok(Value::Integer(4)));
+        assert_eq!(run("COUNT(x)", &values), Ok(Value::Integer(3)));
+        assert_eq!(run("count(DISTINCT x)", &values), Ok(Value::Integer(2)));
+        assert_eq!(run("SUM(x)", &values), Ok(Value::Integer(7)));
+        assert_eq!(run("AVG(DISTINCT x)", &values), Ok(Value::Float(2.0)));
+        assert_eq!(run("MIN(x)", &values), Ok(Value::Integer(1)));
+        assert_eq!(run("MAX(x)", &values), Ok(Value::Integer(3)));
+        assert_eq!(run("SUM(x)", &[Value::Null]), Ok(Value::Null));
+        assert_eq!(run("COUNT(x)", &[]), Ok(Value::Integer(0)));
+        // Integer sums overflow like integer addition does, but averages don't
+        let overflow = [Value::Integer(i64::MAX), Value::Integer(i64::MAX)];
+        assert_eq!(run("SUM(x)", &overflow), Err(QueryError::NumericOverflow { expr: None }));
+        assert_eq!(run("AVG(x)", &overflow), Ok(Value::Float(i64::MAX as f64)));
+        assert_eq!(run("SUM(x)", &[Value::Integer(i64::MAX), Value::Float(1.0)]), Ok(Value::Float(i64::MAX as f64 + 1.0)));
+    }
+
+    #[test]
+    fn test_invalid_aggregate_arguments() {
+        assert!(matches!(run("SUM(x)", &[Value::from("a")]), Err(QueryError::InvalidArguments { .. })));
+        let parse = |sql: &str| Aggregate::parse(&call(sql), &FunctionRegistry::default()).map(|aggregate| aggregate.is_some());
+        assert!(matches!(parse("SUM(*)"), Err(QueryError::InvalidArguments { .. })));
+        assert!(matches!(parse("MAX(a, b)"), Err(QueryError::InvalidArguments { .. })));
+        assert!(matches!(parse("COUNT(*, a)"), Err(QueryError::InvalidArguments { .. })));
+        assert_eq!(parse("UPPER(a)"), Ok(false));
+    }
+
+    #[test]
+    fn test_user_defined_aggregate() {
+        let values = [Value::Integer(3), Value::Null, Value::Integer(1), Value::Integer(3), Value::from("10")];
+        assert_eq!(run("median(x)", &values), Ok(Value::Float(3.0)));
+        assert_eq!(run("MEDIAN(DISTINCT x)", &values), Ok(Value::Float(3.0)));
+        assert_eq!(run("median(x)", &values[..3]), Ok(Value::Float(2.0)));
+        assert_eq!(run("median(x)", &[Value::Null]), Ok(Value::Null));
+        assert!(matches!(run("median(x)", &[Value::from("abc")]), Err(QueryError::InvalidArguments { .. })));
+        assert!(matches!(run("median(x, x)", &values), Err(QueryError::InvalidArguments { .. })));
+
+        let mut left = Median::init();
+        Accumulator::update(&mut left, &[Value::Integer(1)]).unwrap();
+        let mut right = Median::init();
+        Accumulator::update(&mut right, &[Value::Integer(5)]).unwrap();
+        Accumulator::update(&mut right, &[Value::Integer(4)]).unwrap();
+        Accumulator::merge(&mut left, right).unwrap();
+        assert_eq!(Accumulator::finalize(&left), Ok(Value::Float(4.0)));
+    }
+
+    #[test]
+    fn test_running_aggregate() {
+        let function = call("median(DISTINCT x)");
+        let mut functions = FunctionRegistry::default();
+        let signature = Signature::new(vec![ArgType::Numeric], Returns::Type(DataType::Float));
+        functions.register_aggregate::<Median>("median", signature).unwrap();
+        let aggregate = Aggregate::parse(&function, &functions).unwrap().unwrap();
+        let values = [Value::Integer(4), Value::Integer(4), Value::Null, Value::Integer(1), Value::Integer(9)];
+        let rows: Vec<&[Value]> = values.iter().map(std::slice::from_ref).collect();
+        let mut running = aggregate.running().unwrap();
+        let mut prefix = |end| running.evaluate(&rows, end, |_, row| Ok(row[0].clone()));
+        assert_eq!(prefix(0), Ok(Value::Null));
+        assert_eq!(prefix(2), Ok(Value::Float(4.0)));
+        assert_eq!(prefix(4), Ok(Value::Float(2.5)));
+        assert_eq!(prefix(5), Ok(Value::Float(4.0)));
+        // A frame that shrinks is accumulated again from the start
+        assert_eq!(prefix(3), Ok(Value::Float(4.0)));
+        assert_eq!(prefix(4), Ok(Value::Float(2.5)));
+        assert!(Aggregate::parse(&call("SUM(x)"), &functions).unwrap().unwrap().running().is_none());
+    }
+}
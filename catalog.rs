@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use sqlparser::ast::ObjectName;
+
+use crate::aggregate::Accumulator;
+use crate::error::QueryError;
+use crate::functions::{FunctionError, FunctionRegistry, Signature};
+use crate::schema::Table;
+use crate::value::Value;
+
+// The namespace tables are added to, and looked in first, when no namespace is given
+pub const DEFAULT_NAMESPACE: &str = "public";
+
+// All the tables a query can refer to, grouped into namespaces (e.g. `school.student`), and the
+// functions it can call. Names are matched case-insensitively, like unquoted SQL identifiers.
+#[derive(Default)]
+pub struct Catalog {
+    namespaces: BTreeMap<String, BTreeMap<String, Table>>,
+    functions: FunctionRegistry,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Catalog::default()
+    }
+
+    // Add a table to a namespace, replacing any table with the same name
+    pub fn add_table_in(&mut self, namespace: &str, table: Table) -> &mut Self {
+        self.namespaces
+            .entry(namespace.to_lowercase())
+            .or_default()
+            .insert(table.name.to_lowercase(), table);
+        self
+    }
+
+    pub fn table(&self, namespace: &str, name: &str) -> Option<&Table> {
+        self.namespaces.get(&namespace.to_lowercase())?.get(&name.to_lowercase())
+    }
+
+    // Every table with its namespace, ordered by namespace then name
+    pub fn tables(&self) -> impl Iterator<Item = (&str, &Table)> {
+        self.namespaces
+            .iter()
+            .flat_map(|(namespace, tables)| tables.values().map(move |table| (namespace.as_str(), table)))
+    }
+
+    // The names a query could use for each table: bare in the default namespace, qualified elsewhere
+    pub fn table_names(&self) -> Vec<String> {
+        self.tables()
+            .map(|(namespace, table)| match namespace {
+                DEFAULT_NAMESPACE => table.name.clone(),
+                _ => format!("{}.{}", namespace, table.name),
+            })
+            .collect()
+    }
+
+    pub fn functions(&self) -> &FunctionRegistry {
+        &self.functions
+    }
+
+    // Make a scalar function callable from queries, e.g.
+    //
+    //   catalog.register_function("grade_letter", Signature::new(vec![ArgType::Numeric], Returns::Type(DataType::Text)), |args| ...)
+    //
+    // Calls are resolved and their arguments checked against the signature like those of built-in functions.
+    pub fn register_function<F>(&mut self, name: &str, signature: Signature, implementation: F) -> Result<&mut Self, FunctionError>
+    where
+        F: Fn(&[Value]) -> Result<Value, QueryError> + 'static,
+    {
+        self.functions.register(name, signature, implementation)?;
+        Ok(self)
+    }
+
+    // Make an aggregate callable from queries, e.g. `catalog.register_aggregate::<Median>("median", signature)`;
+    // it can be used wherever COUNT or SUM can
+    pub fn register_aggregate<A: Accumulator + 'static>(
+        &mut self,
+        name: &str,
+        signature: Signature,
+    ) -> Result<&mut Self, FunctionError> {
+        self.functions.register_aggregate::<A>(name, signature)?;
+        Ok(self)
+    }
+
+    // Find the table a FROM clause refers to. A bare name is looked up in the default namespace
+    // first, then in any namespace as long as only one of them has a table by that name.
+    pub fn resolve(&self, name: &ObjectName) -> Result<&Table, QueryError> {
+        let unknown = || QueryError::UnknownTable(name.to_string());
+        match name.0.as_slice() {
+            [namespace, table] => self.table(&namespace.value, &table.value).ok_or_else(unknown),
+            [table] => {
+                if let Some(found) = self.table(DEFAULT_NAMESPACE, &table.value) {
+                    return Ok(found);
+                }
+                let mut matches = self.tables().filter(|(_, t)| t.name.eq_ignore_ascii_case(&table.value));
+                match (matches.next(), matches.next()) {
+                    (Some((_, found)), None) => Ok(found),
+                    (Some(_), Some(_)) => Err(QueryError::AmbiguousTable(name.to_string())),
+                    _ => Err(unknown()),
+                }
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+    use sqlparser::ast::Ident;
+
+    fn name(parts: &[&str]) -> ObjectName {
+        ObjectName(parts.iter().map(|part| Ident::new(*part)).collect())
+    }
+
+    #[test]
+    fn test_resolve_bare_and_qualified_names() {
+        let mut catalog = Catalog::new();
+        catalog.add_table_in(DEFAULT_NAMESPACE, Table::new("course", Schema::default()));
+        catalog.add_table_in("school", Table::new("student", Schema::default()));
+
+        assert_eq!(catalog.resolve(&name(&["Course"])).unwrap().name, "course");
+        assert_eq!(catalog.resolve(&name(&["student"])).unwrap().name, "student");
+        assert_eq!(catalog.resolve(&name(&["school", "student"])).unwrap().name, "student");
+        assert!(matches!(catalog.resolve(&name(&["school", "course"])), Err(QueryError::UnknownTable(_))));
+        assert_eq!(catalog.table_names(), vec!["course", "school.student"]);
+    }
+
+    #[test]
+    fn test_bare_name_in_several_namespaces_is_ambiguous() {
+        let mut catalog = Catalog::new();
+        catalog.add_table_in("school", Table::new("student", Schema::default()));
+        catalog.add_table_in("archive", Table::new("student", Schema::default()));
+        assert!(matches!(catalog.resolve(&name(&["student"])), Err(QueryError::AmbiguousTable(_))));
+    }
+}
@@ -0,0 +1,405 @@
+use sqlparser::dialect::GenericDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, TokenWithLocation, Tokenizer};
+
+use crate::error::QueryError;
+
+// A range of characters on one line of the SQL text; line and column are 1-based like sqlparser's
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: u64,
+    pub column: u64,
+    pub width: usize,
+}
+
+// How serious a finding is: errors reject the query, warnings are reported alongside the result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// A rustc-style report: the message, the offending source line with the span underlined, and help notes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    pub label: Option<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Error, message: message.into(), span: None, label: None, help: Vec::new() }
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    // Build a diagnostic for a rejected query, locating the error in the SQL text where possible
+    pub fn from_error(err: &QueryError, sql: &str) -> Self {
+        let diagnostic = Diagnostic::new(err.to_string());
+        match err {
+            QueryError::Parse { line, column, .. } => {
+                let span = token_span_at(sql, *line, *column)
+                    .unwrap_or(Span { line: *line, column: *column, width: 1 });
+                diagnostic.with_span(Some(span)).with_label("unexpected token")
+            }
+            QueryError::EmptyQuery => diagnostic,
+            QueryError::MultipleStatements(_) => diagnostic
+                .with_span(second_statement_span(sql))
+                .with_label("second statement starts here")
+                .with_help("run one query at a time"),
+            QueryError::UnsupportedStatement(kind) => diagnostic
+                .with_span(find_span(sql, kind))
+                .with_label("not a SELECT query"),
+            QueryError::UnsupportedQuery(query) => diagnostic
+                .with_span(find_span(sql, query))
+                .with_label("only a single SELECT is supported"),
+            QueryError::MissingFrom => diagnostic
+                .with_span(missing_from_span(sql))
+                .with_label("expected FROM before this"),
+            QueryError::UnknownTable(name) => diagnostic
+                .with_span(find_span(sql, name))
+                .with_label("unknown table"),
+            QueryError::AmbiguousTable(name) => diagnostic
+                .with_span(find_span(sql, name))
+                .with_label("found in several namespaces"),
+            QueryError::UnsupportedRelation(relation) => diagnostic
+                .with_span(find_span(sql, relation))
+                .with_label("not supported by the validator"),
+            QueryError::UnknownColumn { column, clause, suggestion, .. } => {
+                let diagnostic = diagnostic.with_span(find_span_in(sql, clause, column)).with_label("unknown column");
+                match suggestion {
+                    Some(suggestion) => diagnostic.with_help(format!("did you mean `{}`?", suggestion)),
+                    None => diagnostic,
+                }
+            }
+            QueryError::AmbiguousColumn(column) => diagnostic
+                .with_span(find_span(sql, column))
+                .with_label("found in more than one table"),
+            QueryError::ColumnAliasCount { relation, .. } => diagnostic
+                .with_span(find_span(sql, relation))
+                .with_label("too many column aliases"),
+            QueryError::NotGrouped { column, clause } => diagnostic
+                .with_span(find_span_in(sql, clause, column))
+                .with_label("not grouped")
+                .with_help(format!("add `{}` to the GROUP BY clause or wrap it in an aggregate such as MAX", column)),
+            QueryError::MisplacedAggregate { aggregate, clause } => {
+                let diagnostic = diagnostic.with_span(find_span(sql, aggregate)).with_label("aggregate not allowed here");
+                match clause.as_str() {
+                    "WHERE" => diagnostic.with_help("filter on aggregates with a HAVING clause instead"),
+                    _ => diagnostic,
+                }
+            }
+            QueryError::HavingWithoutGrouping => diagnostic
+                .with_span(find_span(sql, "HAVING"))
+                .with_label("nothing is grouped")
+                .with_help("use WHERE to filter individual rows"),
+            QueryError::MisplacedWindow { function, .. } => diagnostic
+                .with_span(find_span(sql, function))
+                .with_label("window function not allowed here"),
+            QueryError::InvalidWindow { function, .. } => diagnostic
+                .with_span(find_span(sql, function))
+                .with_label("invalid window"),
+            QueryError::InvalidArguments { function, .. } => diagnostic
+                .with_span(find_span(sql, function))
+                .with_label("invalid arguments"),
+            QueryError::InvalidOrdinal { position, count } => diagnostic
+                .with_span(find_span(sql, &format!("BY {}", position)).or_else(|| find_span(sql, &format!(", {}", position))))
+                .with_label("no such output column")
+                .with_help(format!("use a position from 1 to {}", count)),
+            QueryError::OrderByNotInDistinct(expr) => diagnostic
+                .with_span(find_span(sql, expr))
+                .with_label("not in the select list")
+                .with_help(format!("add `{}` to the select list or remove it from ORDER BY", expr)),
+            QueryError::DistinctOnOrder(expr) => diagnostic
+                .with_span(find_span(sql, expr))
+                .with_label("sorted before the DISTINCT ON expressions")
+                .with_help("start ORDER BY with the DISTINCT ON expressions"),
+            QueryError::InvalidLimit { clause, value } => diagnostic
+                .with_span(find_span(sql, value).or_else(|| find_span(sql, clause)))
+                .with_label("not a non-negative integer"),
+            QueryError::UnboundParameter(name) => diagnostic
+                .with_span(find_span(sql, name))
+                .with_label("no value given"),
+            QueryError::TiesWithoutOrderBy => diagnostic
+                .with_span(find_span(sql, "WITH TIES"))
+                .with_label("needs an ORDER BY")
+                .with_help("add an ORDER BY clause"),
+            QueryError::SubqueryColumns(_) => diagnostic
+                .with_span(find_span(sql, "(SELECT"))
+                .with_label("subquery returns more than one column"),
+            QueryError::SubqueryRows(_) => diagnostic
+                .with_span(find_span(sql, "(SELECT"))
+                .with_label("subquery returns more than one row")
+                .with_help("use LIMIT 1 or an aggregate such as MAX to return a single row"),
+            QueryError::InvalidOperands { operator, .. } => diagnostic
+                .with_span(find_span(sql, operator))
+                .with_label("invalid operands"),
+            QueryError::NotACondition { expr, .. } => diagnostic
+                .with_span(find_span(sql, expr))
+                .with_label("expected a boolean"),
+            QueryError::NumericOverflow { expr } => diagnostic
+                .with_span(expr.as_deref().and_then(|expr| find_span(sql, expr)))
+                .with_label("result does not fit in an integer"),
+            QueryError::DivisionByZero { expr } => diagnostic
+                .with_span(expr.as_deref().and_then(|expr| find_span(sql, expr)))
+                .with_label("divides by zero"),
+            QueryError::UnknownFunction(name) => diagnostic
+                .with_span(find_span(sql, name))
+                .with_label("unknown function"),
+            QueryError::InvalidResult { function, .. } => diagnostic
+                .with_span(find_span(sql, function))
+                .with_label("returned the wrong type"),
+            QueryError::TypeMismatch { expr, .. } => diagnostic
+                .with_span(expr.as_deref().and_then(|expr| find_span(sql, expr)))
+                .with_label("incompatible types"),
+            QueryError::InvalidPattern { pattern, .. } => diagnostic
+                .with_span(find_span(sql, &format!("'{}'", pattern)))
+                .with_label("invalid pattern"),
+            QueryError::InvalidCast { .. } => diagnostic.with_span(find_span(sql, "CAST")).with_label("invalid cast"),
+            QueryError::InvalidLiteral { value, .. } => diagnostic
+                .with_span(find_span(sql, &format!("'{}'", value)))
+                .with_label("invalid literal"),
+            QueryError::UnsupportedExpression(expr) => diagnostic
+                .with_span(find_span(sql, expr))
+                .with_label("not supported by the validator"),
+        }
+    }
+
+    // Render the diagnostic against the SQL it refers to, e.g.
+    //
+    //   error: query is missing a FROM clause
+    //    --> 1:13
+    //     |
+    //   1 | SELECT name WHERE major = Math;
+    //     |             ^^^^^ expected FROM before this
+    //     |
+    //     = help: string literals must be quoted: 'Math'
+    pub fn render(&self, sql: &str) -> String {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let mut out = format!("{}: {}\n", level, self.message);
+
+        let source_line = self
+            .span
+            .and_then(|span| sql.lines().nth(span.line.saturating_sub(1) as usize).map(|text| (span, text)));
+        let gutter = source_line.map_or(1, |(span, _)| span.line.to_string().len());
+        let pad = " ".repeat(gutter);
+
+        if let Some((span, text)) = source_line {
+            // Keep tabs in the indentation so the carets still line up with the source
+            let indent: String = text
+                .chars()
+                .take(span.column.saturating_sub(1) as usize)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let available = text.chars().count().saturating_sub(indent.chars().count());
+            let width = span.width.min(available).max(1);
+
+            out.push_str(&format!("{}--> {}:{}\n", pad, span.line, span.column));
+            out.push_str(&format!("{} |\n", pad));
+            out.push_str(&format!("{} | {}\n", span.line, text));
+            out.push_str(&format!("{} | {}{}", pad, indent, "^".repeat(width)));
+            if let Some(label) = &self.label {
+                out.push_str(&format!(" {}", label));
+            }
+            out.push('\n');
+            if !self.help.is_empty() {
+                out.push_str(&format!("{} |\n", pad));
+            }
+        }
+
+        for help in &self.help {
+            out.push_str(&format!("{} = help: {}\n", pad, help));
+        }
+        out
+    }
+}
+
+// Tokenize SQL keeping locations, dropping whitespace; None if the text does not tokenize
+fn significant_tokens(sql: &str) -> Option<Vec<TokenWithLocation>> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, sql).tokenize_with_location().ok()?;
+    Some(tokens.into_iter().filter(|t| !matches!(t.token, Token::Whitespace(_))).collect())
+}
+
+fn token_width(token: &Token) -> usize {
+    token.to_string().chars().count()
+}
+
+// The span of the token that starts exactly at the given location
+pub fn token_span_at(sql: &str, line: u64, column: u64) -> Option<Span> {
+    significant_tokens(sql)?
+        .into_iter()
+        .find(|t| t.location.line == line && t.location.column == column)
+        .map(|t| Span { line, column, width: token_width(&t.token) })
+}
+
+// Find the first place the SQL contains the same token sequence as `fragment`, comparing
+// case-insensitively so that re-printed AST nodes (e.g. `LIKE` for `like`) still match
+pub fn find_span(sql: &str, fragment: &str) -> Option<Span> {
+    find_tokens(&significant_tokens(sql)?, fragment)
+}
+
+// Like find_span, but looking in one clause of the query first (SELECT, FROM, WHERE, GROUP BY, HAVING or
+// ORDER BY), so that e.g. a GROUP BY column is not confused with a select-list alias of the same name.
+// Only the clauses of the outermost query are known; anything else falls back to the whole text.
+pub fn find_span_in(sql: &str, clause: &str, fragment: &str) -> Option<Span> {
+    let tokens = significant_tokens(sql)?;
+    let keyword = clause.split_whitespace().next().unwrap_or(clause);
+    let mut depth = 0;
+    let mut start = None;
+    let mut end = tokens.len();
+    for (index, token) in tokens.iter().enumerate() {
+        match &token.token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::Word(w)
+                if depth == 0 && start.is_none() && w.quote_style.is_none() && w.value.eq_ignore_ascii_case(keyword) =>
+            {
+                start = Some(index);
+            }
+            Token::Word(w) if depth == 0 && start.is_some() && CLAUSE_KEYWORDS.contains(&w.keyword) => {
+                end = index;
+                break;
+            }
+            Token::SemiColon if start.is_some() => {
+                end = index;
+                break;
+            }
+            _ => {}
+        }
+    }
+    start.and_then(|start| find_tokens(&tokens[start..end], fragment)).or_else(|| find_tokens(&tokens, fragment))
+}
+
+// The keywords that end a clause of a SELECT
+const CLAUSE_KEYWORDS: [Keyword; 10] = [
+    Keyword::FROM,
+    Keyword::WHERE,
+    Keyword::GROUP,
+    Keyword::HAVING,
+    Keyword::WINDOW,
+    Keyword::QUALIFY,
+    Keyword::ORDER,
+    Keyword::LIMIT,
+    Keyword::OFFSET,
+    Keyword::FETCH,
+];
+
+fn find_tokens(haystack: &[TokenWithLocation], fragment: &str) -> Option<Span> {
+    let needle: Vec<String> = significant_tokens(fragment)?
+        .iter()
+        .map(|t| t.token.to_string().to_lowercase())
+        .collect();
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+
+    let start = haystack.windows(needle.len()).position(|window| {
+        window.iter().zip(&needle).all(|(t, n)| t.token.to_string().to_lowercase() == *n)
+    })?;
+    let first = &haystack[start];
+    let last = &haystack[start + needle.len() - 1];
+
+    // A fragment that spans several lines is underlined up to the end of its first line
+    let width = if last.location.line == first.location.line {
+        (last.location.column - first.location.column) as usize + token_width(&last.token)
+    } else {
+        usize::MAX
+    };
+    Some(Span { line: first.location.line, column: first.location.column, width })
+}
+
+// FROM belongs right after the projection, so point at the first clause keyword that follows it,
+// or at the end of the query when there is none
+fn missing_from_span(sql: &str) -> Option<Span> {
+    let tokens = significant_tokens(sql)?;
+    let clause = tokens.iter().find(|t| match &t.token {
+        Token::Word(w) => matches!(
+            w.keyword,
+            Keyword::WHERE | Keyword::GROUP | Keyword::HAVING | Keyword::ORDER | Keyword::LIMIT
+        ),
+        Token::SemiColon => true,
+        _ => false,
+    });
+    match clause {
+        Some(t) => Some(Span { line: t.location.line, column: t.location.column, width: token_width(&t.token) }),
+        None => tokens.last().map(|t| Span {
+            line: t.location.line,
+            column: t.location.column + token_width(&t.token) as u64,
+            width: 1,
+        }),
+    }
+}
+
+// The first token after a statement-separating ';'
+fn second_statement_span(sql: &str) -> Option<Span> {
+    let tokens = significant_tokens(sql)?;
+    let separator = tokens.iter().position(|t| t.token == Token::SemiColon)?;
+    tokens[separator + 1..]
+        .iter()
+        .find(|t| t.token != Token::SemiColon)
+        .map(|t| Span { line: t.location.line, column: t.location.column, width: token_width(&t.token) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_span_matches_reprinted_expression() {
+        let sql = "SELECT *\nFROM student WHERE id like '1'";
+        let span = find_span(sql, "id LIKE '1'").unwrap();
+        assert_eq!(span, Span { line: 2, column: 20, width: 11 });
+    }
+
+    #[test]
+    fn test_find_span_in_clause() {
+        let sql = "SELECT major AS m, COUNT(*) FROM student GROUP BY m";
+        assert_eq!(find_span(sql, "m"), Some(Span { line: 1, column: 17, width: 1 }));
+        assert_eq!(find_span_in(sql, "GROUP BY", "m"), Some(Span { line: 1, column: 51, width: 1 }));
+        // A clause the query does not have falls back to searching the whole text
+        assert_eq!(find_span_in(sql, "HAVING", "m"), Some(Span { line: 1, column: 17, width: 1 }));
+    }
+
+    #[test]
+    fn test_render_underlines_span() {
+        let sql = "SELECT name WHERE major = Math;";
+        let rendered = Diagnostic::from_error(&QueryError::MissingFrom, sql)
+            .with_help("string literals must be quoted: 'Math'")
+            .render(sql);
+        assert_eq!(
+            rendered,
+            "error: query is missing a FROM clause\n \
+             --> 1:13\n  \
+             |\n\
+             1 | SELECT name WHERE major = Math;\n  \
+             |             ^^^^^ expected FROM before this\n  \
+             |\n  \
+             = help: string literals must be quoted: 'Math'\n"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+
+// Every reason the validator can reject a query, so callers can tell *why* it was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    // The SQL text could not be tokenized or parsed; line and column are 1-based
+    Parse { message: String, line: u64, column: u64 },
+    // The input contained no statement at all (e.g. an empty string or a lone ';')
+    EmptyQuery,
+    // More than one statement was given, but only a single query can be validated
+    MultipleStatements(usize),
+    // The statement parsed, but it is not a query (INSERT, CREATE, ...)
+    UnsupportedStatement(String),
+    // The query is not a plain SELECT (UNION, VALUES, ...)
+    UnsupportedQuery(String),
+    // A SELECT without a FROM clause
+    MissingFrom,
+    // The FROM clause names a table that does not exist
+    UnknownTable(String),
+    // An unqualified table name that exists in more than one namespace
+    AmbiguousTable(String),
+    // A FROM item other than a named table (table functions, UNNEST, ...)
+    UnsupportedRelation(String),
+    // A column that is not part of the table(s) it was looked up in (several are listed comma-separated);
+    // clause is where it was used (SELECT, WHERE, ...) and suggestion the closest existing column, if any
+    UnknownColumn { column: String, table: String, clause: String, suggestion: Option<String> },
+    // An unqualified column name that more than one table in FROM has
+    AmbiguousColumn(String),
+    // A table alias lists more column names than the table has
+    ColumnAliasCount { relation: String, expected: usize, found: usize },
+    // A column used outside of an aggregate in a grouped query that is not one of the GROUP BY columns,
+    // and the clause (SELECT, HAVING or ORDER BY) it was used in
+    NotGrouped { column: String, clause: String },
+    // An aggregate used where rows have not been grouped yet, e.g. in WHERE
+    MisplacedAggregate { aggregate: String, clause: String },
+    // A HAVING clause in a query with neither GROUP BY nor aggregates
+    HavingWithoutGrouping,
+    // A window function outside of the select list and ORDER BY, e.g. in WHERE
+    MisplacedWindow { function: String, clause: String },
+    // A window call whose function or frame cannot be used, e.g. OVER on a scalar function
+    InvalidWindow { function: String, message: String },
+    // A function called with the wrong number or kind of arguments, e.g. SUM(*)
+    InvalidArguments { function: String, message: String },
+    // An ORDER BY position that is not between 1 and the number of output columns
+    InvalidOrdinal { position: String, count: usize },
+    // With SELECT DISTINCT, an ORDER BY expression that is not one of the output columns
+    OrderByNotInDistinct(String),
+    // An ORDER BY expression where a DISTINCT ON expression has to come first
+    DistinctOnOrder(String),
+    // A LIMIT, OFFSET or FETCH count that is not a non-negative integer constant or parameter
+    InvalidLimit { clause: String, value: String },
+    // A parameter such as $2 that no value was given for
+    UnboundParameter(String),
+    // FETCH ... WITH TIES needs an ORDER BY to know which rows tie
+    TiesWithoutOrderBy,
+    // A subquery used as an IN list or a value that returns more than one column
+    SubqueryColumns(usize),
+    // A subquery used as a value returned more than one row
+    SubqueryRows(usize),
+    // An operator applied to values it does not work on, e.g. 'abc' * 2; unary operators have no left side
+    InvalidOperands { operator: String, left: Option<String>, right: String },
+    // A WHERE, HAVING or ON condition that evaluated to something other than a boolean
+    NotACondition { expr: String, found: String },
+    // Integer arithmetic whose result does not fit in 64 bits. Errors raised while evaluating carry the
+    // expression they were raised in, once it is known (see `within`).
+    NumericOverflow { expr: Option<String> },
+    DivisionByZero { expr: Option<String> },
+    // A call to a function the evaluator does not know
+    UnknownFunction(String),
+    // A user-defined function returned a value that does not fit its declared return type
+    InvalidResult { function: String, found: String, expected: String },
+    // Two values of types that cannot be compared or combined, e.g. INTEGER and TEXT 'abc'
+    TypeMismatch { left: String, right: String, expr: Option<String> },
+    // A LIKE, SIMILAR TO or regex pattern that cannot be compiled, e.g. one with unbalanced parentheses
+    InvalidPattern { pattern: String, message: String },
+    // A CAST between types that do not convert into each other, e.g. a BOOLEAN to a DATE
+    InvalidCast { from: String, to: String },
+    // A typed literal whose text is not a valid value of its type, e.g. DATE '2023-02-30'
+    InvalidLiteral { value: String, data_type: String },
+    // An expression the evaluator does not know how to handle
+    UnsupportedExpression(String),
+}
+
+impl QueryError {
+    // Record the expression an evaluation error was raised in, so diagnostics can point at it. The innermost
+    // expression wins: errors that already name one are passed on unchanged.
+    pub fn within(mut self, expr: &impl fmt::Display) -> Self {
+        if let QueryError::NumericOverflow { expr: found @ None }
+        | QueryError::DivisionByZero { expr: found @ None }
+        | QueryError::TypeMismatch { expr: found @ None, .. } = &mut self
+        {
+            *found = Some(expr.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Parse { message, line, column } => {
+                write!(f, "syntax error at line {}, column {}: {}", line, column, message)
+            }
+            QueryError::EmptyQuery => write!(f, "no SQL statement was given"),
+            QueryError::MultipleStatements(count) => {
+                write!(f, "expected a single statement, found {}", count)
+            }
+            QueryError::UnsupportedStatement(kind) => {
+                write!(f, "only SELECT queries are supported, found {} statement", kind)
+            }
+            QueryError::UnsupportedQuery(query) => write!(f, "unsupported query: {}", query),
+            QueryError::MissingFrom => write!(f, "query is missing a FROM clause"),
+            QueryError::UnknownTable(name) => write!(f, "table '{}' does not exist", name),
+            QueryError::AmbiguousTable(name) => {
+                write!(f, "table name '{}' is ambiguous, qualify it with a namespace", name)
+            }
+            QueryError::UnsupportedRelation(relation) => write!(f, "unsupported FROM item: {}", relation),
+            QueryError::UnknownColumn { column, table, .. } if table.contains(", ") => {
+                write!(f, "column '{}' does not exist in any of the tables {}", column, table)
+            }
+            QueryError::UnknownColumn { column, table, .. } => {
+                write!(f, "column '{}' does not exist in table '{}'", column, table)
+            }
+            QueryError::AmbiguousColumn(column) => {
+                write!(f, "column reference '{}' is ambiguous, qualify it with a table name", column)
+            }
+            QueryError::ColumnAliasCount { relation, expected, found } => {
+                write!(f, "'{}' has {} columns but {} column aliases were given", relation, expected, found)
+            }
+            QueryError::NotGrouped { column, .. } => write!(
+                f,
+                "column '{}' must appear in the GROUP BY clause or be used in an aggregate function",
+                column
+            ),
+            QueryError::MisplacedAggregate { aggregate, clause } => {
+                write!(f, "aggregate functions are not allowed in {}, found {}", clause, aggregate)
+            }
+            QueryError::HavingWithoutGrouping => {
+                write!(f, "HAVING can only be used with GROUP BY or aggregate functions")
+            }
+            QueryError::MisplacedWindow { function, clause } => {
+                write!(f, "window functions are not allowed in {}, found {}", clause, function)
+            }
+            QueryError::InvalidWindow { function, message } => write!(f, "invalid window function {}: {}", function, message),
+            QueryError::InvalidArguments { function, message } => {
+                write!(f, "invalid arguments to {}: {}", function, message)
+            }
+            QueryError::InvalidOrdinal { position, count } => {
+                write!(f, "ORDER BY position {} is not in the select list, which has {} columns", position, count)
+            }
+            QueryError::OrderByNotInDistinct(expr) => {
+                write!(f, "for SELECT DISTINCT, ORDER BY expression '{}' must appear in the select list", expr)
+            }
+            QueryError::DistinctOnOrder(expr) => {
+                write!(f, "DISTINCT ON expressions must match the initial ORDER BY expressions, found '{}'", expr)
+            }
+            QueryError::InvalidLimit { clause, value } => {
+                write!(f, "{} must be a non-negative integer constant or parameter, found {}", clause, value)
+            }
+            QueryError::UnboundParameter(name) => write!(f, "no value was given for parameter {}", name),
+            QueryError::TiesWithoutOrderBy => write!(f, "FETCH ... WITH TIES requires an ORDER BY clause"),
+            QueryError::SubqueryColumns(count) => {
+                write!(f, "subquery must return only one column, found {}", count)
+            }
+            QueryError::SubqueryRows(count) => {
+                write!(f, "subquery used as an expression must return at most one row, found {}", count)
+            }
+            QueryError::InvalidOperands { operator, left: Some(left), right } => {
+                write!(f, "operator {} cannot be applied to {} and {}", operator, left, right)
+            }
+            QueryError::InvalidOperands { operator, left: None, right } => {
+                write!(f, "operator {} cannot be applied to {}", operator, right)
+            }
+            QueryError::NotACondition { expr, found } => {
+                write!(f, "'{}' is not a condition, it evaluates to {}", expr, found)
+            }
+            QueryError::NumericOverflow { .. } => write!(f, "integer out of range"),
+            QueryError::DivisionByZero { .. } => write!(f, "division by zero"),
+            QueryError::UnknownFunction(name) => write!(f, "function {} does not exist", name),
+            QueryError::InvalidResult { function, found, expected } => {
+                write!(f, "function {} returned {}, but is declared to return {}", function, found, expected)
+            }
+            QueryError::TypeMismatch { left, right, .. } => {
+                write!(f, "cannot compare {} with {}", left, right)
+            }
+            QueryError::InvalidPattern { pattern, message } => {
+                write!(f, "invalid pattern '{}': {}", pattern, message)
+            }
+            QueryError::InvalidCast { from, to } => write!(f, "cannot cast {} to {}", from, to),
+            QueryError::InvalidLiteral { value, data_type } => {
+                write!(f, "'{}' is not a valid {}", value, data_type)
+            }
+            QueryError::UnsupportedExpression(expr) => {
+                write!(f, "unsupported expression: {}", expr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
@@ -0,0 +1,568 @@
+use std::cmp::Ordering;
+
+use sqlparser::ast::{self, BinaryOperator, Expr, Query, UnaryOperator};
+
+use crate::error::QueryError;
+use crate::functions::{scalar_call, FunctionRegistry};
+use crate::pattern::Pattern;
+use crate::relation::{resolve_column, resolve_computed, Field};
+use crate::result_set::ResultSet;
+use crate::value::{DataType, Value};
+
+// Runs a nested query such as the one in `x IN (SELECT ...)` for one row of the enclosing query. The
+// evaluator cannot do that itself, as it takes the catalog and the whole query pipeline, so the caller
+// hands it one.
+pub type Subquery<'a> = dyn Fn(&Query, &OuterRow) -> Result<ResultSet, QueryError> + 'a;
+
+// The row of an enclosing query a subquery runs for, whose columns a correlated subquery can use.
+// Subqueries nested more than one level deep see every enclosing row, the nearest one first.
+#[derive(Clone, Copy)]
+pub struct OuterRow<'a> {
+    pub fields: &'a [Field],
+    pub values: &'a [Value],
+    pub outer: Option<&'a OuterRow<'a>>,
+}
+
+impl OuterRow<'_> {
+    // The value of a column from the nearest enclosing row that has it
+    pub fn column(&self, idents: &[ast::Ident]) -> Result<Option<Value>, QueryError> {
+        match resolve_column(self.fields, idents)? {
+            Some(index) => Ok(Some(self.values[index].clone())),
+            None => match self.outer {
+                Some(outer) => outer.column(idents),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+// What an expression is evaluated in: the fields that describe each row, the functions it can call,
+// how to run subqueries (without a runner they are unsupported) and, inside a subquery, the row of
+// the enclosing query it runs for
+#[derive(Clone, Copy)]
+pub struct Scope<'a> {
+    pub fields: &'a [Field],
+    pub functions: &'a FunctionRegistry,
+    pub subquery: Option<&'a Subquery<'a>>,
+    pub outer: Option<&'a OuterRow<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(fields: &'a [Field], functions: &'a FunctionRegistry) -> Self {
+        Scope { fields, functions, subquery: None, outer: None }
+    }
+
+    pub fn with_subquery(self, subquery: &'a Subquery<'a>) -> Self {
+        Scope { subquery: Some(subquery), ..self }
+    }
+
+    pub fn with_outer(self, outer: Option<&'a OuterRow<'a>>) -> Self {
+        Scope { outer, ..self }
+    }
+
+    // The same functions and subqueries, for rows described by other fields
+    pub fn with_fields<'b>(self, fields: &'b [Field]) -> Scope<'b>
+    where
+        'a: 'b,
+    {
+        Scope { fields, ..self }
+    }
+
+    // Whether a column reference that is not one of the fields names a column of an enclosing row
+    pub fn is_outer_column(&self, idents: &[ast::Ident]) -> Result<bool, QueryError> {
+        match self.outer {
+            Some(outer) => Ok(outer.column(idents)?.is_some()),
+            None => Ok(false),
+        }
+    }
+}
+
+// Evaluate a condition (WHERE, HAVING, JOIN ... ON) for one row: only TRUE keeps the row, while FALSE
+// and UNKNOWN (NULL) both drop it
+pub fn evaluate_predicate(expr: &Expr, scope: Scope, row: &[Value]) -> Result<bool, QueryError> {
+    match evaluate(expr, scope, row)? {
+        Value::Bool(b) => Ok(b),
+        Value::Null => Ok(false),
+        other => Err(QueryError::NotACondition { expr: expr.to_string(), found: other.describe() }),
+    }
+}
+
+// Evaluate an expression against one row, giving a typed value; expressions the evaluator does not
+// support are an error. Columns that cannot be resolved (unknown columns that were only reported as
+// warnings) evaluate to NULL. Overflows, divisions by zero and type mismatches name the innermost
+// expression they happened in.
+pub fn evaluate(expr: &Expr, scope: Scope, row: &[Value]) -> Result<Value, QueryError> {
+    evaluate_node(expr, scope, row).map_err(|err| err.within(expr))
+}
+
+fn evaluate_node(expr: &Expr, scope: Scope, row: &[Value]) -> Result<Value, QueryError> {
+    match expr {
+        Expr::Identifier(id) => column(scope, std::slice::from_ref(id), row),
+        Expr::CompoundIdentifier(ids) => column(scope, ids, row),
+        Expr::Value(literal) => Value::from_literal(literal),
+        Expr::TypedString { data_type, value } => typed_literal(data_type, value),
+        Expr::Nested(expr) => evaluate(expr, scope, row),
+        Expr::UnaryOp { op: UnaryOperator::Not, expr: operand } => match evaluate(operand, scope, row)? {
+            Value::Bool(b) => Ok(Value::Bool(!b)),
+            Value::Null => Ok(Value::Null),
+            other => Err(QueryError::InvalidOperands { operator: "NOT".to_string(), left: None, right: other.describe() }),
+        },
+        Expr::UnaryOp { op: op @ (UnaryOperator::Minus | UnaryOperator::Plus), expr: operand } => {
+            let value = evaluate(operand, scope, row)?;
+            match (op, numeric(&value)) {
+                (_, Some(Value::Null)) => Ok(Value::Null),
+                (UnaryOperator::Minus, Some(Value::Integer(i))) => {
+                    i.checked_neg().map(Value::Integer).ok_or(QueryError::NumericOverflow { expr: None })
+                }
+                (UnaryOperator::Minus, Some(Value::Float(x))) => Ok(Value::Float(-x)),
+                (_, Some(number)) => Ok(number),
+                (_, None) => Err(QueryError::InvalidOperands {
+                    operator: op.to_string(),
+                    left: None,
+                    right: value.describe(),
+                }),
+            }
+        }
+        Expr::BinaryOp { left, op, right } => match op {
+            // Three-valued logic: FALSE AND x is FALSE and TRUE OR x is TRUE even when x is NULL (UNKNOWN),
+            // otherwise any NULL makes the result NULL
+            BinaryOperator::And | BinaryOperator::Or => {
+                let decisive = *op == BinaryOperator::Or;
+                let l = truth_value(op, evaluate(left, scope, row)?)?;
+                if l == Some(decisive) {
+                    return Ok(Value::Bool(decisive));
+                }
+                let r = truth_value(op, evaluate(right, scope, row)?)?;
+                Ok(match (l, r) {
+                    (_, Some(r)) if r == decisive => Value::Bool(decisive),
+                    (Some(_), Some(_)) => Value::Bool(!decisive),
+                    _ => Value::Null,
+                })
+            }
+            BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq => compare(op, &evaluate(left, scope, row)?, &evaluate(right, scope, row)?),
+            BinaryOperator::Plus
+            | BinaryOperator::Minus
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo => arithmetic(op, evaluate(left, scope, row)?, evaluate(right, scope, row)?),
+            // `||` is NULL if either side is, and otherwise joins the text of both sides
+            BinaryOperator::StringConcat => match (evaluate(left, scope, row)?, evaluate(right, scope, row)?) {
+                (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+                (l, r) => Ok(Value::Text(format!("{}{}", l, r))),
+            },
+            BinaryOperator::PGRegexMatch
+            | BinaryOperator::PGRegexIMatch
+            | BinaryOperator::PGRegexNotMatch
+            | BinaryOperator::PGRegexNotIMatch => {
+                let negated = matches!(op, BinaryOperator::PGRegexNotMatch | BinaryOperator::PGRegexNotIMatch);
+                let case_insensitive = matches!(op, BinaryOperator::PGRegexIMatch | BinaryOperator::PGRegexNotIMatch);
+                let (text, pattern) = (evaluate(left, scope, row)?, evaluate(right, scope, row)?);
+                pattern_match(&op.to_string(), negated, text, pattern, |p| Pattern::regex(p, case_insensitive))
+            }
+            _ => Err(QueryError::UnsupportedExpression(expr.to_string())),
+        },
+        Expr::Like { negated, expr: text, pattern, escape_char } => {
+            let (text, pattern) = (evaluate(text, scope, row)?, evaluate(pattern, scope, row)?);
+            pattern_match("LIKE", *negated, text, pattern, |p| Pattern::like(p, *escape_char, false))
+        }
+        Expr::ILike { negated, expr: text, pattern, escape_char } => {
+            let (text, pattern) = (evaluate(text, scope, row)?, evaluate(pattern, scope, row)?);
+            pattern_match("ILIKE", *negated, text, pattern, |p| Pattern::like(p, *escape_char, true))
+        }
+        Expr::SimilarTo { negated, expr: text, pattern, escape_char } => {
+            let (text, pattern) = (evaluate(text, scope, row)?, evaluate(pattern, scope, row)?);
+            pattern_match("SIMILAR TO", *negated, text, pattern, |p| Pattern::similar_to(p, *escape_char))
+        }
+        Expr::InList { expr: operand, list, negated } => {
+            let value = evaluate(operand, scope, row)?;
+            let found = in_values(&value, list.iter().map(|item| evaluate(item, scope, row)))?;
+            Ok(negate(found, *negated))
+        }
+        Expr::InSubquery { expr: operand, subquery, negated } => {
+            let value = evaluate(operand, scope, row)?;
+            let result = run_subquery(expr, subquery, scope, row)?;
+            if result.columns.len() != 1 {
+                return Err(QueryError::SubqueryColumns(result.columns.len()));
+            }
+            let found = in_values(&value, result.rows.into_iter().map(|mut row| Ok(row.swap_remove(0))))?;
+            Ok(negate(found, *negated))
+        }
+        // A scalar subquery gives the single value it returns, or NULL when it returns no rows
+        Expr::Subquery(subquery) => {
+            let result = run_subquery(expr, subquery, scope, row)?;
+            if result.columns.len() != 1 {
+                return Err(QueryError::SubqueryColumns(result.columns.len()));
+            }
+            match result.rows.len() {
+                0 => Ok(Value::Null),
+                1 => Ok(result.rows[0][0].clone()),
+                count => Err(QueryError::SubqueryRows(count)),
+            }
+        }
+        Expr::Exists { subquery, negated } => {
+            let result = run_subquery(expr, subquery, scope, row)?;
+            Ok(Value::Bool(result.rows.is_empty() == *negated))
+        }
+        // `x BETWEEN low AND high` is `x >= low AND x <= high`, so a NULL bound can still give FALSE
+        Expr::Between { expr: operand, negated, low, high } => {
+            let value = evaluate(operand, scope, row)?;
+            let above = compare(&BinaryOperator::GtEq, &value, &evaluate(low, scope, row)?)?;
+            let below = compare(&BinaryOperator::LtEq, &value, &evaluate(high, scope, row)?)?;
+            let between = match (above, below) {
+                (Value::Bool(false), _) | (_, Value::Bool(false)) => Value::Bool(false),
+                (Value::Bool(true), Value::Bool(true)) => Value::Bool(true),
+                _ => Value::Null,
+            };
+            Ok(negate(between, *negated))
+        }
+        Expr::IsNull(operand) => Ok(Value::Bool(evaluate(operand, scope, row)? == Value::Null)),
+        Expr::IsNotNull(operand) => Ok(Value::Bool(evaluate(operand, scope, row)? != Value::Null)),
+        Expr::IsDistinctFrom(left, right) => {
+            Ok(Value::Bool(evaluate(left, scope, row)?.is_distinct_from(&evaluate(right, scope, row)?)?))
+        }
+        Expr::IsNotDistinctFrom(left, right) => {
+            Ok(Value::Bool(!evaluate(left, scope, row)?.is_distinct_from(&evaluate(right, scope, row)?)?))
+        }
+        // IS [NOT] TRUE/FALSE/UNKNOWN never return NULL themselves
+        Expr::IsTrue(operand)
+        | Expr::IsNotTrue(operand)
+        | Expr::IsFalse(operand)
+        | Expr::IsNotFalse(operand)
+        | Expr::IsUnknown(operand)
+        | Expr::IsNotUnknown(operand) => {
+            let value = match evaluate(operand, scope, row)? {
+                Value::Bool(b) => Some(b),
+                Value::Null => None,
+                other => return Err(QueryError::NotACondition { expr: operand.to_string(), found: other.describe() }),
+            };
+            Ok(Value::Bool(match expr {
+                Expr::IsTrue(_) => value == Some(true),
+                Expr::IsNotTrue(_) => value != Some(true),
+                Expr::IsFalse(_) => value == Some(false),
+                Expr::IsNotFalse(_) => value != Some(false),
+                Expr::IsUnknown(_) => value.is_none(),
+                _ => value.is_some(),
+            }))
+        }
+        Expr::Case { operand, conditions, results, else_result } => {
+            // `CASE x WHEN v ...` picks the first branch whose value equals x, `CASE WHEN c ...` the first
+            // whose condition holds
+            let operand = match operand {
+                Some(operand) => Some(evaluate(operand, scope, row)?),
+                None => None,
+            };
+            for (when, then) in conditions.iter().zip(results) {
+                let matched = match &operand {
+                    Some(value) => value.compare(&evaluate(when, scope, row)?)? == Some(Ordering::Equal),
+                    None => evaluate_predicate(when, scope, row)?,
+                };
+                if matched {
+                    return evaluate(then, scope, row);
+                }
+            }
+            match else_result {
+                Some(expr) => evaluate(expr, scope, row),
+                None => Ok(Value::Null),
+            }
+        }
+        Expr::Function(_) | Expr::Substring { .. } | Expr::Trim { .. } | Expr::Ceil { .. } | Expr::Floor { .. } => {
+            // Aggregates have already been computed for the group the row stands for
+            if let Some(index) = resolve_computed(scope.fields, expr) {
+                return Ok(row[index].clone());
+            }
+            let call = scalar_call(expr)?.ok_or_else(|| QueryError::UnsupportedExpression(expr.to_string()))?;
+            let function = scope.functions.get(&call.name).ok_or_else(|| QueryError::UnknownFunction(call.name.clone()))?;
+            let mut args = Vec::new();
+            for arg in call.args {
+                args.push(evaluate(arg, scope, row)?);
+            }
+            // SUBSTRING(x FOR n) starts at the first character
+            if let Expr::Substring { substring_from: None, substring_for: Some(_), .. } = expr {
+                args.insert(1, Value::Integer(1));
+            }
+            function.call(&args)
+        }
+        Expr::Cast { expr: operand, data_type } => match DataType::from_sql(data_type) {
+            Some(target) => evaluate(operand, scope, row)?.cast(target),
+            None => Err(QueryError::UnsupportedExpression(expr.to_string())),
+        },
+        _ => Err(QueryError::UnsupportedExpression(expr.to_string())),
+    }
+}
+
+// An operand of a logical operator as TRUE, FALSE or UNKNOWN (None, for NULL)
+fn truth_value(op: &BinaryOperator, value: Value) -> Result<Option<bool>, QueryError> {
+    match value {
+        Value::Bool(b) => Ok(Some(b)),
+        Value::Null => Ok(None),
+        other => Err(QueryError::InvalidOperands { operator: op.to_string(), left: None, right: other.describe() }),
+    }
+}
+
+// Comparisons use the typed rules of Value::compare; comparing with NULL gives NULL (UNKNOWN)
+fn compare(op: &BinaryOperator, left: &Value, right: &Value) -> Result<Value, QueryError> {
+    let ordering = match left.compare(right)? {
+        Some(ordering) => ordering,
+        None => return Ok(Value::Null),
+    };
+    Ok(Value::Bool(match op {
+        BinaryOperator::Eq => ordering == Ordering::Equal,
+        BinaryOperator::NotEq => ordering != Ordering::Equal,
+        BinaryOperator::Lt => ordering == Ordering::Less,
+        BinaryOperator::LtEq => ordering != Ordering::Greater,
+        BinaryOperator::Gt => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    }))
+}
+
+// `x IN (...)` is TRUE if x equals one of the values, FALSE if it equals none of them, and NULL when it
+// equals none but x or one of the values is NULL, since they might have been equal. So `1 NOT IN (2, NULL)`
+// is NULL rather than TRUE.
+fn in_values<I>(value: &Value, candidates: I) -> Result<Value, QueryError>
+where
+    I: IntoIterator<Item = Result<Value, QueryError>>,
+{
+    let mut result = Value::Bool(false);
+    for candidate in candidates {
+        match compare(&BinaryOperator::Eq, value, &candidate?)? {
+            Value::Bool(true) => return Ok(Value::Bool(true)),
+            Value::Null => result = Value::Null,
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+// Apply the NOT of NOT IN, NOT BETWEEN and the like; NULL stays NULL
+fn negate(value: Value, negated: bool) -> Value {
+    match value {
+        Value::Bool(b) => Value::Bool(b != negated),
+        other => other,
+    }
+}
+
+// LIKE, SIMILAR TO and the regex operators only match text against text; NULL on either side gives NULL
+fn pattern_match<F>(operator: &str, negated: bool, text: Value, pattern: Value, compile: F) -> Result<Value, QueryError>
+where
+    F: FnOnce(&str) -> Result<Pattern, QueryError>,
+{
+    match (&text, &pattern) {
+        (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+        (Value::Text(text), Value::Text(pattern)) => Ok(Value::Bool(compile(pattern)?.is_match(text) != negated)),
+        _ => Err(QueryError::InvalidOperands {
+            operator: operator.to_string(),
+            left: Some(text.describe()),
+            right: pattern.describe(),
+        }),
+    }
+}
+
+// A column of the row, or else of an enclosing row (in a correlated subquery)
+fn column(scope: Scope, idents: &[ast::Ident], row: &[Value]) -> Result<Value, QueryError> {
+    if let Some(index) = resolve_column(scope.fields, idents)? {
+        return Ok(row[index].clone());
+    }
+    match scope.outer {
+        Some(outer) => Ok(outer.column(idents)?.unwrap_or(Value::Null)),
+        None => Ok(Value::Null),
+    }
+}
+
+// Run a subquery for the row it appears in, which its correlated column references are resolved against
+fn run_subquery(expr: &Expr, query: &Query, scope: Scope, row: &[Value]) -> Result<ResultSet, QueryError> {
+    let run = scope.subquery.ok_or_else(|| QueryError::UnsupportedExpression(expr.to_string()))?;
+    run(query, &OuterRow { fields: scope.fields, values: row, outer: scope.outer })
+}
+
+// Typed literals such as DATE '2024-01-31' or TIMESTAMP '2024-01-31 09:00:00', which read the text as
+// that type like a CAST does
+pub fn typed_literal(data_type: &ast::DataType, value: &str) -> Result<Value, QueryError> {
+    match DataType::from_sql(data_type) {
+        Some(target) => Value::from(value).cast(target),
+        None => Err(QueryError::UnsupportedExpression(format!("{} '{}'", data_type, value))),
+    }
+}
+
+// The value as a number, reading text the way comparisons do (so '2' * 3 is 6); None if it is not one
+fn numeric(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null | Value::Integer(_) | Value::Float(_) => Some(value.clone()),
+        Value::Text(_) => DataType::Integer.coerce(value).or_else(|| DataType::Float.coerce(value)),
+        _ => None,
+    }
+}
+
+// Integer arithmetic stays in integers (dividing truncates, like PostgreSQL) and fails on overflow;
+// anything involving a float is done in floats
+fn arithmetic(op: &BinaryOperator, left: Value, right: Value) -> Result<Value, QueryError> {
+    let (l, r) = match (numeric(&left), numeric(&right)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => {
+            return Err(QueryError::InvalidOperands {
+                operator: op.to_string(),
+                left: Some(left.describe()),
+                right: right.describe(),
+            })
+        }
+    };
+    match (l, r) {
+        (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+        (Value::Integer(a), Value::Integer(b)) => {
+            let result = match op {
+                BinaryOperator::Plus => a.checked_add(b),
+                BinaryOperator::Minus => a.checked_sub(b),
+                BinaryOperator::Multiply => a.checked_mul(b),
+                _ if b == 0 => return Err(QueryError::DivisionByZero { expr: None }),
+                BinaryOperator::Divide => a.checked_div(b),
+                // i64::MIN % -1 is the only remainder that overflows, and it is 0 like any other x % -1
+                _ => Some(a.wrapping_rem(b)),
+            };
+            result.map(Value::Integer).ok_or(QueryError::NumericOverflow { expr: None })
+        }
+        (l, r) => {
+            let (a, b) = (l.as_f64().unwrap_or_default(), r.as_f64().unwrap_or_default());
+            Ok(Value::Float(match op {
+                BinaryOperator::Plus => a + b,
+                BinaryOperator::Minus => a - b,
+                BinaryOperator::Multiply => a * b,
+                _ if b == 0.0 => return Err(QueryError::DivisionByZero { expr: None }),
+                BinaryOperator::Divide => a / b,
+                _ => a % b,
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    fn eval(sql: &str) -> Result<Value, QueryError> {
+        let expr = Parser::new(&GenericDialect {}).try_with_sql(sql).unwrap().parse_expr().unwrap();
+        let fields = vec![Field::new(Some("t".to_string()), "x", Some(DataType::Integer))];
+        evaluate(&expr, Scope::new(&fields, &FunctionRegistry::default()), &[Value::Integer(7)])
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("x + 1"), Ok(Value::Integer(8)));
+        assert_eq!(eval("-x * 2 - 1"), Ok(Value::Integer(-15)));
+        assert_eq!(eval("x / 2"), Ok(Value::Integer(3)));
+        assert_eq!(eval("x % 4"), Ok(Value::Integer(3)));
+        assert_eq!(eval("(-9223372036854775807 - 1) % -1"), Ok(Value::Integer(0)));
+        let err = eval("(-9223372036854775807 - 1) / -1");
+        assert_eq!(err, Err(QueryError::NumericOverflow { expr: Some("(-9223372036854775807 - 1) / -1".to_string()) }));
+        assert_eq!(eval("x / 2.0"), Ok(Value::Float(3.5)));
+        assert_eq!(eval("(x + 1) * '2'"), Ok(Value::Integer(16)));
+        assert_eq!(eval("x + NULL"), Ok(Value::Null));
+        assert_eq!(eval("x / 0"), Err(QueryError::DivisionByZero { expr: Some("x / 0".to_string()) }));
+        let err = eval("1 + (9223372036854775807 + x)");
+        assert_eq!(err, Err(QueryError::NumericOverflow { expr: Some("9223372036854775807 + x".to_string()) }));
+        assert!(matches!(eval("x + 'abc'"), Err(QueryError::InvalidOperands { .. })));
+    }
+
+    #[test]
+    fn test_logic_and_case() {
+        assert_eq!(eval("NOT (x > 5 AND x < 10)"), Ok(Value::Bool(false)));
+        assert_eq!(eval("x = 1 OR -x = -7"), Ok(Value::Bool(true)));
+        assert_eq!(eval("CASE WHEN x < 5 THEN 'small' WHEN x < 10 THEN 'medium' ELSE 'large' END"), Ok(Value::from("medium")));
+        assert_eq!(eval("CASE x WHEN 1 THEN 'one' WHEN 7 THEN 'seven' END"), Ok(Value::from("seven")));
+        assert_eq!(eval("CASE x WHEN 1 THEN 'one' END"), Ok(Value::Null));
+        assert!(matches!(eval("x AND TRUE"), Err(QueryError::InvalidOperands { .. })));
+        assert!(matches!(eval("INTERVAL '1' DAY IS TRUE"), Err(QueryError::UnsupportedExpression(_))));
+    }
+
+    #[test]
+    fn test_three_valued_logic() {
+        assert_eq!(eval("x = NULL"), Ok(Value::Null));
+        assert_eq!(eval("NOT (x <> NULL)"), Ok(Value::Null));
+        assert_eq!(eval("x = NULL AND x = 1"), Ok(Value::Bool(false)));
+        assert_eq!(eval("x = NULL AND x = 7"), Ok(Value::Null));
+        assert_eq!(eval("x = NULL OR x = 7"), Ok(Value::Bool(true)));
+        assert_eq!(eval("x = NULL OR x = 1"), Ok(Value::Null));
+        assert_eq!(eval("NULL IS NULL AND x IS NOT NULL"), Ok(Value::Bool(true)));
+        assert_eq!(eval("x IS DISTINCT FROM NULL"), Ok(Value::Bool(true)));
+        assert_eq!(eval("NULL IS NOT DISTINCT FROM NULL"), Ok(Value::Bool(true)));
+        assert_eq!(eval("(x = NULL) IS UNKNOWN"), Ok(Value::Bool(true)));
+        assert_eq!(eval("(x = NULL) IS NOT FALSE"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_strings_and_functions() {
+        assert_eq!(eval("'x' || t.x"), Ok(Value::from("x7")));
+        assert_eq!(eval("'x' || NULL"), Ok(Value::Null));
+        assert_eq!(eval("upper('abc') || LOWER('DeF')"), Ok(Value::from("ABCdef")));
+        assert_eq!(eval("LENGTH('héllo')"), Ok(Value::Integer(5)));
+        assert!(matches!(eval("UPPER(x)"), Err(QueryError::InvalidArguments { .. })));
+        assert_eq!(eval("SUBSTRING('Charlie' FROM 2 FOR 3) || SUBSTRING('Charlie' FOR 2)"), Ok(Value::from("harCh")));
+        assert_eq!(eval("TRIM(LEADING 'x' FROM 'xxAx') || TRIM('  B  ')"), Ok(Value::from("AxB")));
+        assert_eq!(eval("CEIL(x / 2.0) + FLOOR(2.5)"), Ok(Value::Float(6.0)));
+        assert_eq!(eval("COALESCE(NULL, x) + ABS(-1) + ROUND(2.4)"), Ok(Value::Float(10.0)));
+        assert_eq!(eval("CAST(x AS TEXT) || CAST('3' AS INTEGER)"), Ok(Value::from("73")));
+        assert!(matches!(eval("CAST(x AS DATE)"), Err(QueryError::InvalidCast { .. })));
+        assert_eq!(eval("frobnicate(x)"), Err(QueryError::UnknownFunction("FROBNICATE".to_string())));
+    }
+
+    #[test]
+    fn test_in_and_between() {
+        assert_eq!(eval("x IN (1, 7)"), Ok(Value::Bool(true)));
+        assert_eq!(eval("x NOT IN (1, 2)"), Ok(Value::Bool(true)));
+        assert_eq!(eval("x IN (1, NULL)"), Ok(Value::Null));
+        assert_eq!(eval("x NOT IN (1, NULL)"), Ok(Value::Null));
+        assert_eq!(eval("x IN (7, NULL)"), Ok(Value::Bool(true)));
+        assert_eq!(eval("NULL IN (1, 2)"), Ok(Value::Null));
+        assert_eq!(eval("x BETWEEN 1 AND 10"), Ok(Value::Bool(true)));
+        assert_eq!(eval("x NOT BETWEEN 1 AND 10"), Ok(Value::Bool(false)));
+        assert_eq!(eval("x BETWEEN 8 AND NULL"), Ok(Value::Bool(false)));
+        assert_eq!(eval("x BETWEEN 1 AND NULL"), Ok(Value::Null));
+        assert!(matches!(eval("x IN ('a')"), Err(QueryError::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_in_subquery() {
+        let expr = |sql: &str| Parser::new(&GenericDialect {}).try_with_sql(sql).unwrap().parse_expr().unwrap();
+        let fields = vec![Field::new(Some("t".to_string()), "x", Some(DataType::Integer))];
+        // Stands in for running the query against a catalog: every column holds a 7 and a NULL
+        let subquery = |query: &Query, _: &OuterRow| {
+            let columns = match &*query.body {
+                ast::SetExpr::Select(select) => select.projection.len(),
+                _ => 0,
+            };
+            let mut result = ResultSet::new(vec!["id".to_string(); columns]);
+            result.rows = vec![vec![Value::Integer(7); columns], vec![Value::Null; columns]];
+            Ok(result)
+        };
+        let functions = FunctionRegistry::default();
+        let scope = Scope::new(&fields, &functions).with_subquery(&subquery);
+        let row = [Value::Integer(7)];
+        assert_eq!(evaluate(&expr("x IN (SELECT id FROM t)"), scope, &row), Ok(Value::Bool(true)));
+        assert_eq!(evaluate(&expr("x NOT IN (SELECT id FROM t)"), scope, &[Value::Integer(1)]), Ok(Value::Null));
+        assert_eq!(evaluate(&expr("x IN (SELECT id, id FROM t)"), scope, &row), Err(QueryError::SubqueryColumns(2)));
+        assert!(matches!(
+            evaluate(&expr("x IN (SELECT id FROM t)"), Scope::new(&fields, &FunctionRegistry::default()), &row),
+            Err(QueryError::UnsupportedExpression(_))
+        ));
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert_eq!(eval("'Alice' LIKE 'A%'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("'Alice' NOT LIKE 'a%'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("'Alice' ILIKE 'a%'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("'10%' LIKE '10!%' ESCAPE '!'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("'Bob' SIMILAR TO '(A|B)%'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("'Charlie' ~ 'li'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("'Charlie' !~* '^C'"), Ok(Value::Bool(false)));
+        assert_eq!(eval("NULL LIKE 'A%'"), Ok(Value::Null));
+        assert_eq!(eval("'Alice' NOT LIKE NULL"), Ok(Value::Null));
+        assert!(matches!(eval("x LIKE '7'"), Err(QueryError::InvalidOperands { .. })));
+        assert!(matches!(eval("'Alice' ~ '(A'"), Err(QueryError::InvalidPattern { .. })));
+    }
+}
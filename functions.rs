@@ -0,0 +1,615 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use sqlparser::ast::{DateTimeField, Expr, FunctionArg, FunctionArgExpr, TrimWhereField};
+
+use crate::aggregate::{Accumulator, AggregateFunction, UserAggregate};
+use crate::error::QueryError;
+use crate::value::{DataType, Value};
+use crate::window::WindowFunction;
+
+// What a function parameter accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgType {
+    Any,
+    // An INTEGER or a FLOAT
+    Numeric,
+    Type(DataType),
+}
+
+impl ArgType {
+    // Whether arguments of a type can be passed without a cast; like in comparisons, an INTEGER can be
+    // passed for a FLOAT and a DATE for a TIMESTAMP
+    pub fn accepts(&self, data_type: DataType) -> bool {
+        match self {
+            ArgType::Any => true,
+            ArgType::Numeric => matches!(data_type, DataType::Integer | DataType::Float),
+            ArgType::Type(expected) => {
+                *expected == data_type
+                    || matches!((expected, data_type), (DataType::Float, DataType::Integer) | (DataType::Timestamp, DataType::Date))
+            }
+        }
+    }
+
+    // Convert an argument value for this parameter, reading text as the parameter's type the way
+    // comparisons do (so ABS('-2') is 2); None if the value does not fit
+    pub fn convert(&self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (_, Value::Null) | (ArgType::Any, _) | (ArgType::Numeric, Value::Integer(_) | Value::Float(_)) => {
+                Some(value.clone())
+            }
+            (ArgType::Numeric, Value::Text(_)) => {
+                DataType::Integer.coerce(value).or_else(|| DataType::Float.coerce(value))
+            }
+            (ArgType::Numeric, _) => None,
+            (ArgType::Type(data_type), _) => data_type.coerce(value),
+        }
+    }
+}
+
+impl fmt::Display for ArgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgType::Any => write!(f, "any value"),
+            ArgType::Numeric => write!(f, "a number"),
+            ArgType::Type(data_type) => write!(f, "{}", data_type),
+        }
+    }
+}
+
+// The type a function returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Returns {
+    Type(DataType),
+    // Whatever type the first argument has, e.g. ABS gives an INTEGER for an INTEGER
+    FirstArgument,
+}
+
+// The parameters a function takes and what it returns. Functions are strict by default: a NULL
+// argument makes the result NULL without calling the function at all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub params: Vec<ArgType>,
+    // How many of the parameters have to be given; the rest are optional
+    pub required: usize,
+    // Whether the last parameter can be repeated any number of times
+    pub variadic: bool,
+    pub returns: Returns,
+    pub strict: bool,
+}
+
+impl Signature {
+    pub fn new(params: Vec<ArgType>, returns: Returns) -> Self {
+        Signature { required: params.len(), params, variadic: false, returns, strict: true }
+    }
+
+    // The last `count` parameters can be left out
+    pub fn optional(mut self, count: usize) -> Self {
+        self.required = self.params.len().saturating_sub(count);
+        self
+    }
+
+    pub fn variadic(mut self) -> Self {
+        self.variadic = true;
+        self
+    }
+
+    // The function is called even when an argument is NULL, and deals with NULLs itself
+    pub fn called_on_null(mut self) -> Self {
+        self.strict = false;
+        self
+    }
+
+    // The parameter the argument at `index` is passed to
+    pub fn param(&self, index: usize) -> Option<ArgType> {
+        match self.params.get(index) {
+            Some(param) => Some(*param),
+            None if self.variadic => self.params.last().copied(),
+            None => None,
+        }
+    }
+
+    pub fn check_arity(&self, function: &str, count: usize) -> Result<(), QueryError> {
+        let plural = |n: usize| if n == 1 { format!("{} argument", n) } else { format!("{} arguments", n) };
+        let expected = if self.variadic {
+            format!("at least {}", plural(self.required))
+        } else if self.required == self.params.len() {
+            plural(self.required)
+        } else {
+            format!("{} to {}", self.required, plural(self.params.len()))
+        };
+        if count < self.required || (!self.variadic && count > self.params.len()) {
+            return Err(QueryError::InvalidArguments {
+                function: function.to_string(),
+                message: format!("expected {}, found {}", expected, count),
+            });
+        }
+        Ok(())
+    }
+
+    // Convert arguments to the parameter types
+    pub fn convert_args(&self, function: &str, args: &[Value]) -> Result<Vec<Value>, QueryError> {
+        let mut converted = Vec::new();
+        for (index, arg) in args.iter().enumerate() {
+            let param = self.param(index).unwrap_or(ArgType::Any);
+            match param.convert(arg) {
+                Some(value) => converted.push(value),
+                None => return Err(invalid_argument(function, index, param, &arg.describe())),
+            }
+        }
+        Ok(converted)
+    }
+
+    // User-defined functions are trusted to take what they declare, but not to return it
+    pub fn check_result(&self, function: &str, result: Value) -> Result<Value, QueryError> {
+        match self.returns {
+            Returns::Type(data_type) if result != Value::Null => {
+                data_type.coerce(&result).ok_or_else(|| QueryError::InvalidResult {
+                    function: function.to_string(),
+                    found: result.describe(),
+                    expected: data_type.to_string(),
+                })
+            }
+            _ => Ok(result),
+        }
+    }
+}
+
+pub type Implementation = Rc<dyn Fn(&[Value]) -> Result<Value, QueryError>>;
+
+// A scalar function queries can call
+#[derive(Clone)]
+pub struct ScalarFunction {
+    pub name: String,
+    pub signature: Signature,
+    // Built-in functions cannot be replaced by user-defined ones
+    pub builtin: bool,
+    implementation: Implementation,
+}
+
+impl ScalarFunction {
+    // Check the arguments against the signature, convert them to the parameter types and call the function
+    pub fn call(&self, args: &[Value]) -> Result<Value, QueryError> {
+        self.signature.check_arity(&self.name, args.len())?;
+        if self.signature.strict && args.contains(&Value::Null) {
+            return Ok(Value::Null);
+        }
+        let converted = self.signature.convert_args(&self.name, args)?;
+        self.signature.check_result(&self.name, (self.implementation)(&converted)?)
+    }
+}
+
+// An argument that does not fit its parameter; `found` describes the argument
+pub fn invalid_argument(function: &str, index: usize, param: ArgType, found: &str) -> QueryError {
+    QueryError::InvalidArguments {
+        function: function.to_string(),
+        message: format!("expected {} for argument {}, found {}", param, index + 1, found),
+    }
+}
+
+// Why a user-defined function could not be registered
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionError {
+    // Not a name queries could call without quoting it, e.g. "grade-letter"
+    InvalidName(String),
+    // The name of a built-in, aggregate or window function
+    Reserved(String),
+}
+
+impl fmt::Display for FunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FunctionError::InvalidName(name) => write!(f, "'{}' is not a valid function name", name),
+            FunctionError::Reserved(name) => write!(f, "{} is a built-in function and cannot be replaced", name),
+        }
+    }
+}
+
+impl std::error::Error for FunctionError {}
+
+// The functions queries can call, by case-insensitive name. A new registry holds the built-in string,
+// numeric and NULL-handling functions; the built-in aggregates are not kept here, only user-defined ones.
+#[derive(Clone)]
+pub struct FunctionRegistry {
+    functions: BTreeMap<String, ScalarFunction>,
+    aggregates: BTreeMap<String, UserAggregate>,
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        let mut registry = FunctionRegistry { functions: BTreeMap::new(), aggregates: BTreeMap::new() };
+        registry.add_builtins();
+        registry
+    }
+}
+
+impl FunctionRegistry {
+    pub fn get(&self, name: &str) -> Option<&ScalarFunction> {
+        self.functions.get(&name.to_uppercase())
+    }
+
+    // A user-defined aggregate
+    pub fn aggregate(&self, name: &str) -> Option<&UserAggregate> {
+        self.aggregates.get(&name.to_uppercase())
+    }
+
+    // The names of all scalar functions and user-defined aggregates, in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.keys().chain(self.aggregates.keys()).cloned().collect();
+        names.sort();
+        names
+    }
+
+    // Add a user-defined function, replacing any user-defined function with the same name. The name has to
+    // be one queries can call without quoting, and cannot be that of a built-in or aggregate function.
+    pub fn register<F>(&mut self, name: &str, signature: Signature, implementation: F) -> Result<&mut Self, FunctionError>
+    where
+        F: Fn(&[Value]) -> Result<Value, QueryError> + 'static,
+    {
+        self.check_name(name)?;
+        self.aggregates.remove(&name.to_uppercase());
+        Ok(self.add(name, signature, false, Rc::new(implementation)))
+    }
+
+    // Add a user-defined aggregate, which can then be used like COUNT or SUM. The same names are allowed
+    // as for scalar functions, and an aggregate replaces any user-defined function with the same name.
+    pub fn register_aggregate<A: Accumulator + 'static>(
+        &mut self,
+        name: &str,
+        signature: Signature,
+    ) -> Result<&mut Self, FunctionError> {
+        self.check_name(name)?;
+        let aggregate = UserAggregate::new::<A>(name, signature);
+        self.functions.remove(&aggregate.name);
+        self.aggregates.insert(aggregate.name.clone(), aggregate);
+        Ok(self)
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), FunctionError> {
+        let mut chars = name.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(FunctionError::InvalidName(name.to_string()));
+        }
+        if self.get(name).is_some_and(|function| function.builtin)
+            || AggregateFunction::from_name(name).is_some()
+            || WindowFunction::from_name(name).is_some()
+        {
+            return Err(FunctionError::Reserved(name.to_uppercase()));
+        }
+        Ok(())
+    }
+
+    fn builtin<F>(&mut self, name: &str, signature: Signature, implementation: F) -> &mut Self
+    where
+        F: Fn(&[Value]) -> Result<Value, QueryError> + 'static,
+    {
+        self.add(name, signature, true, Rc::new(implementation))
+    }
+
+    fn add(&mut self, name: &str, signature: Signature, builtin: bool, implementation: Implementation) -> &mut Self {
+        let name = name.to_uppercase();
+        let function = ScalarFunction { name: name.clone(), signature, builtin, implementation };
+        self.functions.insert(name, function);
+        self
+    }
+
+    fn add_builtins(&mut self) {
+        use ArgType::{Any, Numeric};
+        const TEXT: ArgType = ArgType::Type(DataType::Text);
+        const INTEGER: ArgType = ArgType::Type(DataType::Integer);
+        let text = Returns::Type(DataType::Text);
+
+        self.builtin("UPPER", Signature::new(vec![TEXT], text), |args| Ok(Value::Text(as_text(&args[0]).to_uppercase())))
+            .builtin("LOWER", Signature::new(vec![TEXT], text), |args| Ok(Value::Text(as_text(&args[0]).to_lowercase())))
+            .builtin("LENGTH", Signature::new(vec![TEXT], Returns::Type(DataType::Integer)), |args| {
+                Ok(Value::Integer(as_text(&args[0]).chars().count() as i64))
+            })
+            .builtin("SUBSTRING", Signature::new(vec![TEXT, INTEGER, INTEGER], text).optional(1), substring)
+            .builtin("TRIM", Signature::new(vec![TEXT, TEXT], text).optional(1), |args| trim(args, true, true))
+            .builtin("LTRIM", Signature::new(vec![TEXT, TEXT], text).optional(1), |args| trim(args, true, false))
+            .builtin("RTRIM", Signature::new(vec![TEXT, TEXT], text).optional(1), |args| trim(args, false, true))
+            .builtin("REPLACE", Signature::new(vec![TEXT, TEXT, TEXT], text), |args| {
+                let (text, from, to) = (as_text(&args[0]), as_text(&args[1]), as_text(&args[2]));
+                Ok(Value::Text(if from.is_empty() { text.to_string() } else { text.replace(from, to) }))
+            })
+            // CONCAT skips NULLs rather than returning NULL like `||`
+            .builtin("CONCAT", Signature::new(vec![Any], text).variadic().called_on_null(), |args| {
+                Ok(Value::Text(args.iter().filter(|arg| **arg != Value::Null).map(Value::to_string).collect()))
+            });
+
+        self.builtin("ABS", Signature::new(vec![Numeric], Returns::FirstArgument), |args| match args[0] {
+            Value::Integer(i) => i.checked_abs().map(Value::Integer).ok_or(QueryError::NumericOverflow { expr: None }),
+            ref other => Ok(Value::Float(as_float(other).abs())),
+        })
+        .builtin("ROUND", Signature::new(vec![Numeric, INTEGER], Returns::FirstArgument).optional(1), round)
+        .builtin("CEIL", Signature::new(vec![Numeric], Returns::FirstArgument), |args| {
+            Ok(float_op(&args[0], f64::ceil))
+        })
+        .builtin("FLOOR", Signature::new(vec![Numeric], Returns::FirstArgument), |args| {
+            Ok(float_op(&args[0], f64::floor))
+        })
+        .builtin("MOD", Signature::new(vec![Numeric, Numeric], Returns::FirstArgument), |args| {
+            match (&args[0], &args[1]) {
+                (_, Value::Integer(0)) => Err(QueryError::DivisionByZero { expr: None }),
+                // wrapping_rem only wraps for i64::MIN % -1, which is 0 anyway
+                (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a.wrapping_rem(*b))),
+                (_, b) if as_float(b) == 0.0 => Err(QueryError::DivisionByZero { expr: None }),
+                (a, b) => Ok(Value::Float(as_float(a) % as_float(b))),
+            }
+        });
+
+        self.builtin("COALESCE", Signature::new(vec![Any], Returns::FirstArgument).variadic().called_on_null(), |args| {
+            Ok(args.iter().find(|arg| **arg != Value::Null).cloned().unwrap_or(Value::Null))
+        })
+        .builtin("NULLIF", Signature::new(vec![Any, Any], Returns::FirstArgument).called_on_null(), |args| {
+            match args[0].compare(&args[1])? {
+                Some(Ordering::Equal) => Ok(Value::Null),
+                _ => Ok(args[0].clone()),
+            }
+        });
+    }
+}
+
+// Built-ins get their arguments already converted to the parameter types, so these only ever see the
+// expected variants
+fn as_text(value: &Value) -> &str {
+    match value {
+        Value::Text(text) => text,
+        _ => "",
+    }
+}
+
+fn as_integer(value: &Value) -> i64 {
+    match value {
+        Value::Integer(i) => *i,
+        _ => 0,
+    }
+}
+
+fn as_float(value: &Value) -> f64 {
+    value.as_f64().unwrap_or_default()
+}
+
+// Apply a rounding function to a float; integers are already whole
+fn float_op(value: &Value, op: fn(f64) -> f64) -> Value {
+    match value {
+        Value::Float(x) => Value::Float(op(*x)),
+        other => other.clone(),
+    }
+}
+
+// SUBSTRING(text, start [, count]) with a 1-based start, which may lie before the first character
+fn substring(args: &[Value]) -> Result<Value, QueryError> {
+    let chars: Vec<char> = as_text(&args[0]).chars().collect();
+    let start = as_integer(&args[1]);
+    let end = match args.get(2).map(as_integer) {
+        Some(count) if count < 0 => {
+            return Err(QueryError::InvalidArguments {
+                function: "SUBSTRING".to_string(),
+                message: "negative substring length not allowed".to_string(),
+            })
+        }
+        Some(count) => start.saturating_add(count),
+        None => i64::MAX,
+    };
+    let begin = start.max(1) as usize - 1;
+    let end = (end.max(1) as usize - 1).min(chars.len());
+    Ok(Value::Text(if begin < end { chars[begin..end].iter().collect() } else { String::new() }))
+}
+
+// Strip the given characters (spaces by default) from the start and/or end of the text
+fn trim(args: &[Value], leading: bool, trailing: bool) -> Result<Value, QueryError> {
+    let text = as_text(&args[0]);
+    let characters: Vec<char> = args.get(1).map_or(vec![' '], |chars| as_text(chars).chars().collect());
+    let mut trimmed = text;
+    if leading {
+        trimmed = trimmed.trim_start_matches(characters.as_slice());
+    }
+    if trailing {
+        trimmed = trimmed.trim_end_matches(characters.as_slice());
+    }
+    Ok(Value::Text(trimmed.to_string()))
+}
+
+// ROUND(x [, digits]) rounds half away from zero; negative digits round to tens, hundreds and so on
+fn round(args: &[Value]) -> Result<Value, QueryError> {
+    let digits = args.get(1).map_or(0, as_integer);
+    match &args[0] {
+        Value::Integer(_) if digits >= 0 => Ok(args[0].clone()),
+        // No i64 reaches 5 * 10^19, so anything rounded further is 0
+        Value::Integer(_) if digits < -19 => Ok(Value::Integer(0)),
+        Value::Integer(i) => {
+            let factor = 10i128.pow(digits.unsigned_abs() as u32);
+            let i = i128::from(*i);
+            let rounded = (i + i.signum() * factor / 2) / factor * factor;
+            i64::try_from(rounded).map(Value::Integer).map_err(|_| QueryError::NumericOverflow { expr: None })
+        }
+        value => round_float(as_float(value), digits).map(Value::Float),
+    }
+}
+
+// Floats are rounded in their shortest decimal form, the digits they print with, so 2.345 rounds up to 2.35
+// even though the float nearest to it is a little below. Rounding past the last of those digits leaves the
+// float as it is, however small it is or however many digits are asked for.
+fn round_float(x: f64, digits: i64) -> Result<f64, QueryError> {
+    if x == 0.0 || !x.is_finite() {
+        return Ok(x);
+    }
+    // e.g. "2.345e0", for the significant digits 2345 starting at the ones
+    let text = format!("{:e}", x.abs());
+    let (mantissa, exponent) = text.split_once('e').expect("floats format with an exponent");
+    let significant = mantissa.replace('.', "");
+    let exponent: i64 = exponent.parse().expect("float exponents are integers");
+
+    // How many of the significant digits are kept
+    let keep = (exponent + 1).saturating_add(digits);
+    if keep >= significant.len() as i64 {
+        return Ok(x);
+    }
+    if keep < 0 {
+        return Ok(0.0);
+    }
+    let keep = keep as usize;
+    let mut kept: u64 = significant[..keep].parse().unwrap_or(0);
+    if significant.as_bytes()[keep] >= b'5' {
+        kept += 1;
+    }
+    let rounded: f64 = format!("{}e{}", kept, exponent + 1 - keep as i64).parse().expect("a float in decimal");
+    if rounded.is_infinite() {
+        return Err(QueryError::NumericOverflow { expr: None });
+    }
+    Ok(if x < 0.0 { -rounded } else { rounded })
+}
+
+// A call to a scalar function: the upper-case function name and the arguments it is given
+pub struct Call<'a> {
+    pub name: String,
+    pub args: Vec<&'a Expr>,
+}
+
+// The scalar function call an expression makes, if it is one: either a plain call such as UPPER(name),
+// or one of the calls SQL has special syntax for, such as SUBSTRING(name FROM 2 FOR 3),
+// TRIM(LEADING 'x' FROM name) and CEIL(x). Built-in aggregates and window functions are not scalar calls;
+// user-defined aggregates look like scalar calls here, so callers check for them first.
+pub fn scalar_call(expr: &Expr) -> Result<Option<Call<'_>>, QueryError> {
+    fn call<'a>(name: &str, args: Vec<&'a Expr>) -> Result<Option<Call<'a>>, QueryError> {
+        Ok(Some(Call { name: name.to_string(), args }))
+    }
+    match expr {
+        Expr::Function(function)
+            if function.over.is_none() && AggregateFunction::from_name(&function.name.to_string()).is_none() =>
+        {
+            let name = function.name.to_string().to_uppercase();
+            let invalid = |message: String| QueryError::InvalidArguments { function: name.clone(), message };
+            if function.distinct {
+                return Err(invalid("DISTINCT is only allowed in aggregate functions".to_string()));
+            }
+            let mut args = Vec::new();
+            for arg in &function.args {
+                match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => args.push(expr),
+                    other => return Err(invalid(format!("unexpected argument {}", other))),
+                }
+            }
+            call(&name, args)
+        }
+        Expr::Substring { expr, substring_from, substring_for } => {
+            call("SUBSTRING", [Some(&**expr), substring_from.as_deref(), substring_for.as_deref()].into_iter().flatten().collect())
+        }
+        Expr::Trim { expr, trim_where, trim_what } => {
+            let name = match trim_where {
+                Some(TrimWhereField::Leading) => "LTRIM",
+                Some(TrimWhereField::Trailing) => "RTRIM",
+                Some(TrimWhereField::Both) | None => "TRIM",
+            };
+            call(name, [Some(&**expr), trim_what.as_deref()].into_iter().flatten().collect())
+        }
+        Expr::Ceil { expr, field: DateTimeField::NoDateTime } => call("CEIL", vec![expr]),
+        Expr::Floor { expr, field: DateTimeField::NoDateTime } => call("FLOOR", vec![expr]),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[Value]) -> Result<Value, QueryError> {
+        FunctionRegistry::default().get(name).unwrap().call(args)
+    }
+
+    #[test]
+    fn test_string_functions() {
+        let text = |s: &str| Value::from(s);
+        assert_eq!(call("substring", &[text("Charlie"), Value::Integer(2), Value::Integer(3)]), Ok(text("har")));
+        assert_eq!(call("SUBSTRING", &[text("Charlie"), Value::Integer(-1), Value::Integer(4)]), Ok(text("Ch")));
+        assert_eq!(call("SUBSTRING", &[text("Charlie"), Value::Integer(5)]), Ok(text("lie")));
+        assert_eq!(call("TRIM", &[text("  Bob ")]), Ok(text("Bob")));
+        assert_eq!(call("LTRIM", &[text("xxBobx"), text("x")]), Ok(text("Bobx")));
+        assert_eq!(call("REPLACE", &[text("banana"), text("an"), text("AN")]), Ok(text("bANANa")));
+        assert_eq!(call("CONCAT", &[text("a"), Value::Null, Value::Integer(1)]), Ok(text("a1")));
+        assert_eq!(call("UPPER", &[Value::Null]), Ok(Value::Null));
+    }
+
+    #[test]
+    fn test_numeric_and_null_functions() {
+        assert_eq!(call("ABS", &[Value::Integer(-3)]), Ok(Value::Integer(3)));
+        assert_eq!(call("ABS", &[Value::from("-2.5")]), Ok(Value::Float(2.5)));
+        assert_eq!(call("ROUND", &[Value::Float(2.345), Value::Integer(2)]), Ok(Value::Float(2.35)));
+        assert_eq!(call("ROUND", &[Value::Integer(1250), Value::Integer(-2)]), Ok(Value::Integer(1300)));
+        assert_eq!(call("ROUND", &[Value::Float(12345.6), Value::Integer(308)]), Ok(Value::Float(12345.6)));
+        assert_eq!(call("ROUND", &[Value::Float(1e300), Value::Integer(20)]), Ok(Value::Float(1e300)));
+        assert_eq!(call("ROUND", &[Value::Float(12345.6), Value::Integer(-400)]), Ok(Value::Float(0.0)));
+        assert_eq!(call("ROUND", &[Value::Float(-1250.0), Value::Integer(-2)]), Ok(Value::Float(-1300.0)));
+        assert_eq!(call("ROUND", &[Value::Float(56.0), Value::Integer(-2)]), Ok(Value::Float(100.0)));
+        assert_eq!(call("ROUND", &[Value::Float(2.5)]), Ok(Value::Float(3.0)));
+        // Subnormal floats have digits hundreds of places after the point
+        assert_eq!(call("ROUND", &[Value::Float(1.5e-310), Value::Integer(310)]), Ok(Value::Float(2e-310)));
+        assert_eq!(call("ROUND", &[Value::Float(5e-324), Value::Integer(400)]), Ok(Value::Float(5e-324)));
+        assert_eq!(call("ROUND", &[Value::Float(1.5e-310), Value::Integer(2)]), Ok(Value::Float(0.0)));
+        // However far the digits go either way
+        assert_eq!(call("ROUND", &[Value::Float(2.345), Value::Integer(i64::MAX)]), Ok(Value::Float(2.345)));
+        assert_eq!(call("ROUND", &[Value::Float(2.345), Value::Integer(i64::MIN)]), Ok(Value::Float(0.0)));
+        assert_eq!(call("ROUND", &[Value::Integer(i64::MAX), Value::Integer(i64::MIN)]), Ok(Value::Integer(0)));
+        assert_eq!(call("ROUND", &[Value::Integer(-1250), Value::Integer(-2)]), Ok(Value::Integer(-1300)));
+        let err = call("ROUND", &[Value::Integer(i64::MAX), Value::Integer(-19)]);
+        assert_eq!(err, Err(QueryError::NumericOverflow { expr: None }));
+        let err = call("ROUND", &[Value::Float(f64::MAX), Value::Integer(-308)]);
+        assert_eq!(err, Err(QueryError::NumericOverflow { expr: None }));
+        assert_eq!(call("CEIL", &[Value::Float(1.2)]), Ok(Value::Float(2.0)));
+        assert_eq!(call("FLOOR", &[Value::Float(-1.2)]), Ok(Value::Float(-2.0)));
+        assert_eq!(call("MOD", &[Value::Integer(7), Value::Integer(3)]), Ok(Value::Integer(1)));
+        assert_eq!(call("MOD", &[Value::Integer(i64::MIN), Value::Integer(-1)]), Ok(Value::Integer(0)));
+        let err = call("MOD", &[Value::Integer(7), Value::Integer(0)]);
+        assert_eq!(err, Err(QueryError::DivisionByZero { expr: None }));
+        assert_eq!(call("COALESCE", &[Value::Null, Value::Integer(2), Value::Integer(3)]), Ok(Value::Integer(2)));
+        assert_eq!(call("NULLIF", &[Value::Integer(1), Value::Integer(1)]), Ok(Value::Null));
+        assert_eq!(call("NULLIF", &[Value::Integer(1), Value::Null]), Ok(Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_arity_and_argument_types() {
+        let message = |result: Result<Value, QueryError>| match result {
+            Err(QueryError::InvalidArguments { message, .. }) => message,
+            other => panic!("expected invalid arguments, got {:?}", other),
+        };
+        assert_eq!(message(call("UPPER", &[])), "expected 1 argument, found 0");
+        assert_eq!(message(call("ROUND", &[Value::Integer(1), Value::Integer(1), Value::Integer(1)])), "expected 1 to 2 arguments, found 3");
+        assert_eq!(message(call("COALESCE", &[])), "expected at least 1 argument, found 0");
+        assert_eq!(message(call("ABS", &[Value::from("abc")])), "expected a number for argument 1, found TEXT 'abc'");
+        assert_eq!(message(call("LENGTH", &[Value::Integer(1)])), "expected TEXT for argument 1, found INTEGER 1");
+    }
+
+    #[test]
+    fn test_register_user_function() {
+        let mut registry = FunctionRegistry::default();
+        let signature = Signature::new(vec![ArgType::Numeric], Returns::Type(DataType::Float));
+        registry.register("double_it", signature.clone(), |args| Ok(Value::Integer(as_float(&args[0]) as i64 * 2))).unwrap();
+        let double = registry.get("DOUBLE_IT").unwrap();
+        assert!(!double.builtin);
+        assert_eq!(double.call(&[Value::Integer(4)]), Ok(Value::Float(8.0)));
+
+        registry.register("double_it", signature.clone(), |_| Ok(Value::from("eight"))).unwrap();
+        assert_eq!(
+            registry.get("double_it").unwrap().call(&[Value::Integer(4)]),
+            Err(QueryError::InvalidResult {
+                function: "DOUBLE_IT".to_string(),
+                found: "TEXT 'eight'".to_string(),
+                expected: "FLOAT".to_string(),
+            })
+        );
+
+        let rejected = |name: &str| registry.clone().register(name, signature.clone(), |args| Ok(args[0].clone())).err();
+        assert_eq!(rejected("upper"), Some(FunctionError::Reserved("UPPER".to_string())));
+        assert_eq!(rejected("Sum"), Some(FunctionError::Reserved("SUM".to_string())));
+        for name in ["row_number", "RANK", "dense_rank", "lag", "Lead", "first_value"] {
+            assert_eq!(rejected(name), Some(FunctionError::Reserved(name.to_uppercase())));
+        }
+        assert_eq!(rejected("grade-letter"), Some(FunctionError::InvalidName("grade-letter".to_string())));
+        assert_eq!(rejected("1st"), Some(FunctionError::InvalidName("1st".to_string())));
+    }
+}
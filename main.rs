@@ -1,128 +1,895 @@
+mod aggregate;
+mod catalog;
+mod diagnostic;
+mod error;
+mod eval;
+mod functions;
+mod pattern;
+mod relation;
+mod result_set;
+mod schema;
+mod semantic;
+mod sort;
+mod value;
+mod window;
+
 use sqlparser::dialect::GenericDialect;
-use sqlparser::parser::Parser;
-use sqlparser::ast::{Expr, SelectItem, SetExpr, Statement};
-use std::collections::HashMap;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
+use sqlparser::ast::{
+    self, Distinct, Expr, JoinConstraint, JoinOperator, OrderByExpr, Query, SelectItem, SetExpr, Statement, TableFactor,
+    TableWithJoins,
+};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 use maplit::hashmap;
+use aggregate::{Accumulator, Aggregate};
+use catalog::Catalog;
+use diagnostic::{Diagnostic, Severity};
+use error::QueryError;
+use eval::{OuterRow, Scope};
+use functions::{ArgType, Returns, Signature};
+use relation::{names_column, resolve_column, Field, JoinKind, Relation};
+use result_set::ResultSet;
+use schema::{Column, Schema, Table};
+use semantic::ValidationOptions;
+use sort::SortOrder;
+use value::{DataType, HashKey, Value};
+use window::WindowCall;
+
+// The rows a query produced, along with any problems that were only reported as warnings
+struct QueryResult {
+    result_set: ResultSet,
+    warnings: Vec<QueryError>,
+}
+
+// Parse the SQL text, turning sqlparser's errors into a QueryError that knows where the problem is
+fn parse_sql(sql: &str) -> Result<Vec<Statement>, QueryError> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, sql)
+        .tokenize_with_location()
+        .map_err(|err| QueryError::Parse { message: err.message, line: err.line, column: err.col })?;
+
+    // Keep the non-whitespace tokens around so a parser error can be pinned to a location
+    let significant: Vec<_> = tokens
+        .iter()
+        .filter(|t| !matches!(t.token, Token::Whitespace(_)))
+        .cloned()
+        .collect();
+
+    let mut parser = Parser::new(&dialect).with_tokens_with_locations(tokens);
+    match parser.parse_statements() {
+        Ok(statements) => Ok(statements),
+        Err(err) => {
+            let message = match err {
+                ParserError::TokenizerError(message) | ParserError::ParserError(message) => message,
+                ParserError::RecursionLimitExceeded => "query is nested too deeply".to_string(),
+            };
+
+            // sqlparser reports "Expected ..., found: <token>" where the token was either just consumed
+            // or is the next one to be consumed, so check both neighbours of the parser's position
+            let next = parser.peek_token();
+            let position = significant
+                .iter()
+                .position(|t| t.location == next.location)
+                .unwrap_or(significant.len());
+            let found = message.rsplit("found: ").next().unwrap_or_default();
+            let offending = match position.checked_sub(1).and_then(|i| significant.get(i)) {
+                Some(prev) if prev.token.to_string() == found => Some(prev),
+                _ => significant.get(position),
+            };
+
+            let (line, column) = match offending {
+                Some(token) => (token.location.line, token.location.column),
+                // Ran off the end of the input: point just past the last character
+                None => {
+                    let line = sql.lines().count().max(1);
+                    let column = sql.lines().last().map_or(0, |l| l.chars().count()) + 1;
+                    (line as u64, column as u64)
+                }
+            };
+            Err(QueryError::Parse { message, line, column })
+        }
+    }
+}
+
+// Split rows into groups with equal GROUP BY values, keeping groups in the order they first appear.
+// NULLs are grouped together.
+fn group_rows<'a>(group_by: &[Expr], scope: Scope, rows: Vec<&'a [Value]>) -> Result<Vec<Vec<&'a [Value]>>, QueryError> {
+    let mut indexes: HashMap<Vec<HashKey>, usize> = HashMap::new();
+    let mut groups: Vec<Vec<&[Value]>> = Vec::new();
+    for row in rows {
+        let mut key = Vec::new();
+        for expr in group_by {
+            key.push(eval::evaluate(expr, scope, row)?.key());
+        }
+        let index = *indexes.entry(key).or_insert(groups.len());
+        if index == groups.len() {
+            groups.push(Vec::new());
+        }
+        groups[index].push(row);
+    }
+    Ok(groups)
+}
+
+// Turn the filtered rows into one row per group. Each group row holds the values of the group's first
+// row (so GROUP BY columns can still be referenced) followed by one computed field per aggregate used in
+// the projection, HAVING or ORDER BY. Without GROUP BY all rows form a single group, even when there are none.
+fn aggregate_rows(
+    select: &ast::Select,
+    order_by: &[OrderByExpr],
+    scope: Scope,
+    rows: Vec<&[Value]>,
+) -> Result<Relation, QueryError> {
+    let fields = scope.fields;
+    let mut calls: Vec<&Expr> = Vec::new();
+    let projected = select.projection.iter().filter_map(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+        _ => None,
+    });
+    for expr in projected.chain(&select.having).chain(order_by.iter().map(|order| &order.expr)) {
+        semantic::collect_ungrouped(expr, scope.functions, &mut Vec::new(), &mut calls);
+    }
+    let mut aggregates: Vec<(&Expr, Aggregate)> = Vec::new();
+    for expr in calls {
+        if let Expr::Function(function) = expr
+            && !aggregates.iter().any(|(seen, _)| *seen == expr)
+            && let Some(aggregate) = Aggregate::parse(function, scope.functions)?
+        {
+            aggregates.push((expr, aggregate));
+        }
+    }
+
+    let groups = if select.group_by.is_empty() { vec![rows] } else { group_rows(&select.group_by, scope, rows)? };
+
+    let argument = |expr: &Expr, row: &[Value]| eval::evaluate(expr, scope, row);
+    let mut grouped = Relation {
+        fields: fields.iter().cloned().chain(aggregates.iter().map(|(expr, _)| Field::computed(expr))).collect(),
+        rows: Vec::new(),
+    };
+    for group in &groups {
+        let mut row = match group.first() {
+            Some(first) => first.to_vec(),
+            None => vec![Value::Null; fields.len()],
+        };
+        for (expr, aggregate) in &aggregates {
+            row.push(aggregate.evaluate(group, argument).map_err(|err| err.within(expr))?);
+        }
+        grouped.rows.push(row);
+    }
+    Ok(grouped)
+}
+
+// Compute the window calls in the projection and ORDER BY, adding one hidden field per call to the rows
+fn window_rows(
+    select: &ast::Select,
+    order_by: &[OrderByExpr],
+    context: Scope,
+    mut relation: Relation,
+) -> Result<Relation, QueryError> {
+    let projected = select.projection.iter().filter_map(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+        _ => None,
+    });
+    let mut calls: Vec<&Expr> = Vec::new();
+    for expr in projected.chain(order_by.iter().map(|order| &order.expr)) {
+        for call in semantic::windows_in(expr) {
+            if !calls.contains(&call) {
+                calls.push(call);
+            }
+        }
+    }
 
-type Row = HashMap<String, String>;
+    let mut columns = Vec::new();
+    {
+        let scope = context.with_fields(&relation.fields);
+        let rows: Vec<&[Value]> = relation.rows.iter().map(Vec::as_slice).collect();
+        for call in &calls {
+            if let Expr::Function(function) = call
+                && let Some(window) = WindowCall::parse(function, scope.functions)?
+            {
+                let values = window.evaluate(&rows, |expr, row| eval::evaluate(expr, scope, row));
+                columns.push(values.map_err(|err| err.within(call))?);
+            }
+        }
+    }
+    for (index, row) in relation.rows.iter_mut().enumerate() {
+        row.extend(columns.iter().map(|column| column[index].clone()));
+    }
+    relation.fields.extend(calls.into_iter().map(Field::computed));
+    Ok(relation)
+}
+
+// Whether any of the rows holds the same values as `row`, treating NULLs as equal to each other
+fn find_row<'a>(mut rows: impl Iterator<Item = &'a Vec<Value>>, row: &[Value]) -> Result<bool, QueryError> {
+    rows.try_fold(false, |found, other| {
+        if found {
+            return Ok(true);
+        }
+        for (a, b) in other.iter().zip(row) {
+            if a.is_distinct_from(b)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    })
+}
+
+// Where an output column gets its values from
+#[derive(PartialEq)]
+enum Source<'a> {
+    // A column of the rows, or None for an unknown column that was only reported as a warning
+    Column(Option<usize>),
+    // An expression computed for each row
+    Expr(&'a Expr),
+}
 
-#[allow(dead_code)]
-struct Table {
-    #[allow(dead_code)]
-    name: String,
-    rows: Vec<Row>,
+// What an ORDER BY key sorts on
+#[derive(PartialEq)]
+enum SortKey<'a> {
+    // One of the output columns
+    Output(usize),
+    // An expression evaluated against the rows before projection
+    Expr(&'a Expr),
 }
 
-// Evaluate the 'WHERE' condition for a given row recursivey by handling the logical operators
-fn evaluate_condition(expr: &Expr, row: &Row) -> bool {
+fn sort_key<'a>(expr: &'a Expr, columns: &[String], sources: &[Source]) -> Result<SortKey<'a>, QueryError> {
     match expr {
-        // Handle binary operations like 'column = value' or 'condition AND condition'.
-        Expr::BinaryOp { left, op, right } => {
-            let left_val = &**left;
-            let right_val = &**right;
-
-            match (left_val, right_val) {
-                // Evaluate the expressions where right side is a literal value and the left side is a column identifier
-                (Expr::Identifier(id), Expr::Value(val)) => {
-                    let column = id.value.clone();
-                    let value = val.to_string().trim_matches('\'').to_string();
-                    match op.to_string().as_str() {
-                        "=" => row.get(&column) == Some(&value),
-                        "!=" => row.get(&column) != Some(&value),
-                        _ => false,
-                    }
-                }
-                // Handle the logical AND & OR operators by recursively evaluating their operands
-                _ => {
-                    match op.to_string().as_str() {
-                        "AND" => evaluate_condition(left_val, row) && evaluate_condition(right_val, row),
-                        "OR" => evaluate_condition(left_val, row) || evaluate_condition(right_val, row),
-                        _ => false,
-                    }
-                }
+        Expr::Value(ast::Value::Number(n, _)) => match n.parse::<usize>() {
+            Ok(position) if (1..=columns.len()).contains(&position) => Ok(SortKey::Output(position - 1)),
+            _ => Err(QueryError::InvalidOrdinal { position: n.clone(), count: columns.len() }),
+        },
+        Expr::Identifier(id) => {
+            let matching: Vec<usize> = (0..columns.len()).filter(|&i| names_column(id, &columns[i])).collect();
+            match matching.as_slice() {
+                [] => Ok(SortKey::Expr(expr)),
+                // `SELECT *, id ... ORDER BY id` is fine, but two different columns named alike are not
+                [first, rest @ ..] if rest.iter().all(|i| sources[*i] == sources[*first]) => Ok(SortKey::Output(*first)),
+                _ => Err(QueryError::AmbiguousColumn(id.value.clone())),
             }
         }
-        _ => false,
+        _ => Ok(SortKey::Expr(expr)),
     }
 }
 
-// Next, evaluate a SQL query against a table that is given, by returning the resultant rows and a validity flag
-fn evaluate_query(table: &Table, sql: &str) -> (Vec<Row>, bool) {
-    let dialect = GenericDialect {};
+// Build the relation a FROM clause describes: each item has its JOINs applied left to right, and
+// comma-separated items are cross joined with each other. ON conditions are evaluated in `scope`, with the
+// fields of the joined rows.
+fn build_from(catalog: &Catalog, from: &[TableWithJoins], scope: Scope) -> Result<Relation, QueryError> {
+    let mut result: Option<Relation> = None;
+    for item in from {
+        let mut relation = table_factor(catalog, &item.relation, scope)?;
+        for join in &item.joins {
+            let right = table_factor(catalog, &join.relation, scope)?;
+            relation = apply_join(relation, right, &join.join_operator, scope)?;
+        }
+        result = Some(match result {
+            Some(left) => left.join(relation, JoinKind::Cross, |_, _| Ok(true))?,
+            None => relation,
+        });
+    }
+    result.ok_or(QueryError::MissingFrom)
+}
+
+// Turn a single FROM item into a relation: a catalog table (optionally aliased), a parenthesized join or a
+// subquery, which runs once before the rest of the query
+fn table_factor(catalog: &Catalog, factor: &TableFactor, scope: Scope) -> Result<Relation, QueryError> {
+    match factor {
+        TableFactor::Table { name, alias, .. } => {
+            let table = catalog.resolve(name)?;
+            match alias {
+                Some(alias) => Relation::scan(table, &alias.name.value, &alias.columns),
+                None => Relation::scan(table, &table.name, &[]),
+            }
+        }
+        TableFactor::NestedJoin { table_with_joins, alias } => {
+            let relation = build_from(catalog, std::slice::from_ref(&**table_with_joins), scope)?;
+            Ok(match alias {
+                Some(alias) => relation.requalify(&alias.name.value),
+                None => relation,
+            })
+        }
+        TableFactor::Derived { lateral: false, subquery, alias } => {
+            let run = scope.subquery.ok_or_else(|| QueryError::UnsupportedRelation(factor.to_string()))?;
+            // A derived table cannot see the other tables in FROM, but in a correlated subquery it can
+            // see the rows the subquery runs for
+            let result = run(subquery, &OuterRow { fields: &[], values: &[], outer: scope.outer })?;
+            match alias {
+                Some(alias) => Relation::derived(result, Some(&alias.name.value), &alias.columns),
+                None => Relation::derived(result, None, &[]),
+            }
+        }
+        other => Err(QueryError::UnsupportedRelation(other.to_string())),
+    }
+}
+
+fn apply_join(left: Relation, right: Relation, operator: &JoinOperator, scope: Scope) -> Result<Relation, QueryError> {
+    let (kind, constraint) = match operator {
+        JoinOperator::Inner(constraint) => (JoinKind::Inner, constraint),
+        JoinOperator::LeftOuter(constraint) => (JoinKind::Left, constraint),
+        JoinOperator::RightOuter(constraint) => (JoinKind::Right, constraint),
+        JoinOperator::FullOuter(constraint) => (JoinKind::Full, constraint),
+        JoinOperator::CrossJoin => return left.join(right, JoinKind::Cross, |_, _| Ok(true)),
+        other => return Err(QueryError::UnsupportedRelation(format!("{:?} join", other))),
+    };
+    match constraint {
+        JoinConstraint::On(expr) => left.join(right, kind, |fields, row| eval::evaluate_predicate(expr, scope.with_fields(fields), row)),
+        JoinConstraint::Using(columns) => left.join_using(right, kind, columns),
+        JoinConstraint::Natural => {
+            let columns = left.common_columns(&right);
+            left.join_using(right, kind, &columns)
+        }
+        JoinConstraint::None => left.join(right, kind, |_, _| Ok(true)),
+    }
+}
+
+// A label for the tables in FROM, used when an unqualified column is not found in any of them
+fn from_label(relation: &Relation) -> String {
+    let mut tables: Vec<&str> = Vec::new();
+    for qualifier in relation.fields.iter().filter_map(|field| field.qualifier.as_deref()) {
+        if !tables.contains(&qualifier) {
+            tables.push(qualifier);
+        }
+    }
+    tables.join(", ")
+}
+
+// Next, evaluate a SQL query against the tables of a catalog, by returning the resultant rows and any warnings, or the
+// reason it was rejected; the options say how strict the semantic checks are
+fn evaluate_query_with_options(catalog: &Catalog, sql: &str, options: &ValidationOptions) -> Result<QueryResult, QueryError> {
     // After, attempt to parse a SQL query
-    let ast = match Parser::parse_sql(&dialect, sql) {
-        Ok(ast) => ast,
-        Err(_) => return (vec![], false), // Next, return empty result and false if parsing fails
+    let ast = parse_sql(sql)?;
+
+    // Exactly one statement is expected, and it has to be a Query
+    let statement = match ast.as_slice() {
+        [] => return Err(QueryError::EmptyQuery),
+        [statement] => statement,
+        statements => return Err(QueryError::MultipleStatements(statements.len())),
+    };
+    let query = match statement {
+        Statement::Query(query) => query,
+        other => {
+            let kind = other.to_string().split_whitespace().next().unwrap_or_default().to_uppercase();
+            return Err(QueryError::UnsupportedStatement(kind));
+        }
+    };
+    run_query(catalog, query, options, None)
+}
+
+// Run one query, which may be the whole statement or a subquery nested inside of it. A subquery runs
+// once for every row of the enclosing query it is used in, and can refer to that row's columns.
+fn run_query(
+    catalog: &Catalog,
+    query: &Query,
+    options: &ValidationOptions,
+    outer: Option<&OuterRow>,
+) -> Result<QueryResult, QueryError> {
+    // Make sure that the query body is a 'Select' statement, possibly in parentheses
+    let select = match &*query.body {
+        SetExpr::Select(select) => select,
+        SetExpr::Query(inner)
+            if query.order_by.is_empty() && query.limit.is_none() && query.offset.is_none() && query.fetch.is_none() =>
+        {
+            return run_query(catalog, inner, options, outer);
+        }
+        other => return Err(QueryError::UnsupportedQuery(other.to_string())),
     };
 
-    // Process the first statement in parsed AST, thereby expecting it to be a Query
-    if let Statement::Query(query) = &ast[0] {
-        // Make sure that the query body is a 'Select' statement
-        if let SetExpr::Select(select) = &*query.body {
-            // Basic check: FROM clause cannot be empty
-            if select.from.is_empty() {
-                return (vec![], false);
+    // Basic check: FROM clause cannot be empty
+    if select.from.is_empty() {
+        return Err(QueryError::MissingFrom);
+    }
+
+    // LIMIT, OFFSET and FETCH counts are known before any rows are read
+    let mut unnamed = 0;
+    let mut count = |expr: &Expr, clause: &str| semantic::row_count(expr, clause, &options.parameters, &mut unnamed);
+    let limit = match &query.limit {
+        Some(expr) => count(expr, "LIMIT")?,
+        None => None,
+    };
+    let offset = match &query.offset {
+        Some(offset) => count(&offset.value, "OFFSET")?.unwrap_or(0),
+        None => 0,
+    };
+    let fetch = match &query.fetch {
+        Some(fetch) if fetch.percent => return Err(QueryError::UnsupportedQuery(fetch.to_string())),
+        Some(fetch) if fetch.with_ties && query.order_by.is_empty() => return Err(QueryError::TiesWithoutOrderBy),
+        // FETCH FIRST ROW ONLY fetches a single row
+        Some(fetch) => match &fetch.quantity {
+            Some(expr) => count(expr, "FETCH")?,
+            None => Some(1),
+        },
+        None => None,
+    };
+    let with_ties = query.fetch.as_ref().is_some_and(|fetch| fetch.with_ties);
+
+    // Subqueries run against the same catalog with the same options. They only see their own FROM
+    // clause, and any warnings they report are passed on (once, though they may run for every row).
+    let nested_warnings = RefCell::new(Vec::new());
+    let subquery = |query: &Query, row: &OuterRow| {
+        let result = run_query(catalog, query, options, Some(row))?;
+        let mut nested = nested_warnings.borrow_mut();
+        for warning in result.warnings {
+            if !nested.contains(&warning) {
+                nested.push(warning);
             }
+        }
+        Ok(result.result_set)
+    };
+
+    // Build the rows of the FROM clause, looking every table up in the catalog
+    let context = Scope::new(&[], catalog.functions()).with_subquery(&subquery).with_outer(outer);
+    let relation = build_from(catalog, &select.from, context)?;
+    let scope = context.with_fields(&relation.fields);
+
+    // GROUP BY positions and aliases stand for the output columns they name
+    let resolved = semantic::resolve_group_by(select, &relation.fields)?;
+    let select = resolved.as_ref().unwrap_or(select);
+
+    // Every column the query mentions has to exist in one of the tables (or, in a correlated subquery, of
+    // the enclosing query)
+    let mut warnings = Vec::new();
+    for err in semantic::check_columns(select, &relation.fields, outer, &from_label(&relation))? {
+        match options.unknown_columns {
+            Severity::Error => return Err(err),
+            Severity::Warning => warnings.push(err),
+        }
+    }
+
+    // Function calls have to name a known function and pass arguments that fit it
+    let clause_exprs = semantic::clause_exprs(select).into_iter().map(|(_, expr)| expr);
+    for expr in clause_exprs.chain(query.order_by.iter().map(|order| &order.expr)) {
+        semantic::check_functions(expr, &relation.fields, catalog.functions())?;
+    }
+
+    // And the comparisons in the WHERE clause have to make sense for the declared column types
+    if let Some(selection) = &select.selection {
+        semantic::check_types(selection, &relation.fields)?;
+    }
 
-            // Verify that the table name in the query matches the table name that is provided
-            let table_name_in_query = select.from[0].relation.to_string().to_lowercase();
-            if table_name_in_query != table.name.to_lowercase() {
-                return (vec![], false);
+    // In a grouped query, aggregates are only computed after WHERE, and only GROUP BY columns can be
+    // used outside of aggregates
+    semantic::check_aggregate_placement(select, catalog.functions())?;
+    let grouped = semantic::is_grouped(select, catalog.functions());
+    if select.having.is_some() && !grouped {
+        match options.having_without_grouping {
+            Severity::Error => return Err(QueryError::HavingWithoutGrouping),
+            Severity::Warning => warnings.push(QueryError::HavingWithoutGrouping),
+        }
+    }
+    semantic::check_window_placement(select)?;
+    semantic::check_grouping(select, &relation.fields, catalog.functions())?;
+    if let Some(having) = &select.having {
+        semantic::check_types(having, &relation.fields)?;
+    }
+
+    let projection = &select.projection;
+    let selection = &select.selection;
+
+    // Next, filter the rows based on 'WHERE' clause, if they are present. A HAVING clause in a query that
+    // is not grouped (only allowed as a warning) filters them too.
+    let mut rows: Vec<&[Value]> = Vec::new();
+    for row in &relation.rows {
+        let keep = match selection {
+            Some(expr) => eval::evaluate_predicate(expr, scope, row)?, // Keep the rows the condition holds for
+            None => true,                                               // If no WHERE clause, include all rows
+        };
+        let keep = match &select.having {
+            Some(having) if keep && !grouped => eval::evaluate_predicate(having, scope, row)?,
+            _ => keep,
+        };
+        if keep {
+            rows.push(row);
+        }
+    }
+
+    // Grouped queries continue with one row per group, which HAVING then filters
+    let output = if grouped {
+        let mut grouped = aggregate_rows(select, &query.order_by, scope, rows)?;
+        if let Some(having) = &select.having {
+            let mut kept = Vec::new();
+            for row in grouped.rows {
+                if eval::evaluate_predicate(having, context.with_fields(&grouped.fields), &row)? {
+                    kept.push(row);
+                }
             }
+            grouped.rows = kept;
+        }
+        grouped
+    } else {
+        Relation { fields: relation.fields.clone(), rows: rows.into_iter().map(<[Value]>::to_vec).collect() }
+    };
+    // Window functions see the rows that are left after grouping
+    let output = window_rows(select, &query.order_by, context, output)?;
+    let fields = &output.fields;
+    let scope = context.with_fields(fields);
 
-            let projection = &select.projection;
-            let selection = &select.selection;
+    // Work out the output columns: '*' expands to the visible columns in FROM order, 't.*' to the
+    // columns of one table, and unknown columns (only possible as warnings) come out as NULL. Other
+    // expressions, and columns of the enclosing query in a subquery, are computed per row and named
+    // after their alias or their SQL text.
+    let mut columns = Vec::new();
+    let mut sources = Vec::new();
+    for item in projection {
+        match item {
+            SelectItem::Wildcard(_) => {
+                for (i, field) in fields.iter().enumerate().filter(|(_, f)| !f.hidden) {
+                    columns.push(field.name.clone());
+                    sources.push(Source::Column(Some(i)));
+                }
+            }
+            SelectItem::QualifiedWildcard(name, _) => {
+                let qualifier = &name.0[name.0.len() - 1].value;
+                let matching: Vec<(usize, &Field)> = fields
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, f)| f.qualifier.as_ref().is_some_and(|q| q.eq_ignore_ascii_case(qualifier)))
+                    .collect();
+                if matching.is_empty() {
+                    return Err(QueryError::UnknownTable(name.to_string()));
+                }
+                for (i, field) in matching {
+                    columns.push(field.name.clone());
+                    sources.push(Source::Column(Some(i)));
+                }
+            }
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                let column = |idents: &[ast::Ident]| match resolve_column(fields, idents)? {
+                    None if scope.is_outer_column(idents)? => Ok::<_, QueryError>(Source::Expr(expr)),
+                    index => Ok(Source::Column(index)),
+                };
+                let (name, source) = match expr {
+                    Expr::Identifier(id) => (id.value.clone(), column(std::slice::from_ref(id))?),
+                    Expr::CompoundIdentifier(ids) => (ids[ids.len() - 1].value.clone(), column(ids)?),
+                    other => (other.to_string(), Source::Expr(other)),
+                };
+                // An alias renames the output column
+                match item {
+                    SelectItem::ExprWithAlias { alias, .. } => columns.push(alias.value.clone()),
+                    _ => columns.push(name),
+                }
+                sources.push(source);
+            }
+        }
+    }
 
-            // Next, filter the table rows based on 'WHERE' clause, if they are present
-            let filtered_rows: Vec<Row> = table
-                .rows
-                .iter()
-                .filter(|row| {
-                    if let Some(expr) = selection {
-                        evaluate_condition(expr, row) // Use the evaluate_condition function to filter the rows
-                    } else {
-                        true // If no WHERE clause, include all rows
-                    }
-                })
-         
-                .map(|row| {
-                    let mut new_row = Row::new();
-                    for item in projection {
-                        match item {
-                            SelectItem::Wildcard(_) => {
-                                for (k, v) in row {
-                                    new_row.insert(k.clone(), v.clone());
-                                }
-                            }
-                            SelectItem::UnnamedExpr(Expr::Identifier(id)) => {
-                                if let Some(val) = row.get(&id.value) {
-                                    new_row.insert(id.value.clone(), val.clone());
-                                }
-                            }
-                            _ => {}
-                        }
+    // ORDER BY keys (and DISTINCT ON expressions) can name an output column (by name, alias or 1-based
+    // position) or be any expression over the rows, including columns that are not projected
+    let distinct_on: &[Expr] = match &select.distinct {
+        Some(Distinct::On(exprs)) => exprs,
+        _ => &[],
+    };
+    let mut keys = Vec::new();
+    let mut on_keys = Vec::new();
+    for (expr, is_order_key) in query
+        .order_by
+        .iter()
+        .map(|order| (&order.expr, true))
+        .chain(distinct_on.iter().map(|expr| (expr, false)))
+    {
+        let key = sort_key(expr, &columns, &sources)?;
+        let clause = if is_order_key { "ORDER BY" } else { "SELECT" };
+        if let SortKey::Expr(expr) = key {
+            let mut identifiers = Vec::new();
+            semantic::collect_identifiers(expr, &mut identifiers);
+            for idents in identifiers {
+                if resolve_column(fields, idents)?.is_none() && !scope.is_outer_column(idents)? {
+                    let err = semantic::unknown_column(idents, fields, &from_label(&relation), clause);
+                    match options.unknown_columns {
+                        Severity::Error => return Err(err),
+                        Severity::Warning => warnings.push(err),
                     }
-                    new_row
-                })
-                .collect();
+                }
+            }
+            if grouped {
+                semantic::check_grouped_expr(select, &relation.fields, expr, catalog.functions(), clause)?;
+            }
+        }
+        if is_order_key { keys.push(key) } else { on_keys.push(key) }
+    }
+    let orders: Vec<SortOrder> = query.order_by.iter().map(SortOrder::from_ast).collect();
+
+    // With DISTINCT the rows are compared on their output values, so the sort keys have to be among them;
+    // with DISTINCT ON the first row of each key is kept, so ORDER BY has to sort on those keys first
+    match &select.distinct {
+        Some(Distinct::Distinct) => {
+            for (order, key) in query.order_by.iter().zip(&keys) {
+                let projected = |expr: &Expr| {
+                    projection.iter().any(|item| {
+                        matches!(item, SelectItem::UnnamedExpr(e) | SelectItem::ExprWithAlias { expr: e, .. } if e == expr)
+                    })
+                };
+                if matches!(key, SortKey::Expr(expr) if !projected(expr)) {
+                    return Err(QueryError::OrderByNotInDistinct(order.expr.to_string()));
+                }
+            }
+        }
+        Some(Distinct::On(_)) => {
+            for (order, key) in query.order_by.iter().zip(&keys).take(on_keys.len()) {
+                if !on_keys.contains(key) {
+                    return Err(QueryError::DistinctOnOrder(order.expr.to_string()));
+                }
+            }
+        }
+        None => {}
+    }
+
+    let mut projected = Vec::new();
+    for row in &output.rows {
+        let mut values = Vec::new();
+        for source in &sources {
+            values.push(match source {
+                Source::Column(Some(i)) => row[*i].clone(),
+                Source::Column(None) => Value::Null,
+                Source::Expr(expr) => eval::evaluate(expr, scope, row)?,
+            });
+        }
+        let evaluate = |key: &SortKey| match key {
+            SortKey::Output(i) => Ok(values[*i].clone()),
+            SortKey::Expr(expr) => eval::evaluate(expr, scope, row),
+        };
+        let key = keys.iter().map(evaluate).collect::<Result<Vec<_>, QueryError>>()?;
+        let on_key = on_keys.iter().map(evaluate).collect::<Result<Vec<_>, QueryError>>()?;
+        projected.push((key, (on_key, values)));
+    }
+    let projected = sort::sort_by_keys(projected, &orders)?;
 
-            return (filtered_rows, true); // Return the result and highlight that it is a valid query
+    // Keep the first row of each set of duplicates, comparing the whole output row for DISTINCT and
+    // just the DISTINCT ON keys otherwise
+    let mut seen: Vec<Vec<Value>> = Vec::new();
+    let mut unique = Vec::new();
+    for (key, (on_key, values)) in projected {
+        let identity = match &select.distinct {
+            Some(Distinct::Distinct) => values.clone(),
+            Some(Distinct::On(_)) => on_key,
+            None => {
+                unique.push((key, values));
+                continue;
+            }
+        };
+        if !find_row(seen.iter(), &identity)? {
+            seen.push(identity);
+            unique.push((key, values));
+        }
+    }
+    let mut projected = unique;
+
+    // Skip OFFSET rows, then keep as many as LIMIT/FETCH allow (the smaller, if both are given).
+    // WITH TIES also keeps the rows that sort equal to the last one kept.
+    projected.drain(..offset.min(projected.len()));
+    if let Some(keep) = [limit, fetch].into_iter().flatten().min() {
+        let mut end = keep.min(projected.len());
+        if with_ties && end > 0 {
+            while end < projected.len()
+                && sort::compare_keys(&projected[end].0, &projected[end - 1].0, &orders)? == Ordering::Equal
+            {
+                end += 1;
+            }
         }
+        projected.truncate(end);
     }
 
-    (vec![], false) // Return the empty result and false for query types that are unsupported
+    let mut result_set = ResultSet::new(columns);
+    result_set.rows = projected.into_iter().map(|(_, values)| values).collect();
+    warnings.extend(nested_warnings.take());
+
+    Ok(QueryResult { result_set, warnings }) // Return the result since it is a valid query
 }
 
-fn main() {
-    let student_table = Table {
-        name: "student".to_string(),
-        rows: vec![
-            hashmap! {"id".to_string() => "1".to_string(), "name".to_string() => "Alice".to_string(), "major".to_string() => "CS".to_string()},
-            hashmap! {"id".to_string() => "2".to_string(), "name".to_string() => "Bob".to_string(), "major".to_string() => "Math".to_string()},
-            hashmap! {"id".to_string() => "3".to_string(), "name".to_string() => "Charlie".to_string(), "major".to_string() => "CS".to_string()},
-        ],
+// Find bare words compared against a column, e.g. `major = Math`, which are almost always string literals missing their quotes
+fn unquoted_literals(expr: &Expr, columns: &HashSet<&str>, found: &mut Vec<String>) {
+    if let Expr::BinaryOp { left, right, .. } = expr {
+        match (&**left, &**right) {
+            (Expr::Identifier(a), Expr::Identifier(b)) | (Expr::Identifier(b), Expr::Identifier(a))
+                if columns.contains(a.value.as_str()) && !columns.contains(b.value.as_str()) =>
+            {
+                found.push(b.value.clone());
+            }
+            _ => {
+                unquoted_literals(left, columns, found);
+                unquoted_literals(right, columns, found);
+            }
+        }
+    }
+}
+
+// Build the diagnostic for a rejected query, adding help notes for the mistakes students make most often
+fn diagnose(catalog: &Catalog, sql: &str, err: &QueryError) -> Diagnostic {
+    let mut diagnostic = Diagnostic::from_error(err, sql);
+    let table_names = catalog.table_names();
+    match err {
+        QueryError::MissingFrom => {
+            if let Some(name) = table_names.first() {
+                diagnostic = diagnostic.with_help(format!("add a FROM clause naming a table, e.g. `FROM {}`", name));
+            }
+        }
+        QueryError::UnknownTable(name) => {
+            // Match on the bare table name, but suggest the name the query can actually use
+            let bare: Vec<String> = catalog.tables().map(|(_, table)| table.name.clone()).collect();
+            let wanted = name.rsplit('.').next().unwrap_or(name);
+            let suggestion = semantic::suggest(wanted, &bare)
+                .and_then(|found| bare.iter().position(|b| *b == found))
+                .map(|index| &table_names[index]);
+            diagnostic = match suggestion {
+                Some(suggestion) => diagnostic.with_help(format!("did you mean `{}`?", suggestion)),
+                None => diagnostic.with_help(format!("the available tables are: {}", table_names.join(", "))),
+            };
+        }
+        QueryError::UnknownFunction(name) => {
+            if let Some(suggestion) = semantic::suggest(name, &catalog.functions().names()) {
+                diagnostic = diagnostic.with_help(format!("did you mean `{}`?", suggestion));
+            }
+        }
+        QueryError::AmbiguousTable(name) => {
+            let qualified: Vec<&String> = table_names.iter().filter(|t| t.ends_with(&format!(".{}", name))).collect();
+            diagnostic = diagnostic.with_help(format!(
+                "use one of: {}",
+                qualified.iter().map(|t| t.as_str()).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        _ => {}
+    }
+
+    // Look for unquoted string literals in the WHERE clause, if the query got far enough to be parsed
+    let select = match parse_sql(sql).as_deref() {
+        Ok([Statement::Query(query)]) => match &*query.body {
+            SetExpr::Select(select) => Some(select.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(select) = select {
+        // Compare against the columns of the tables in FROM, or of every table when they cannot be resolved
+        let subquery = |query: &Query, row: &OuterRow| {
+            run_query(catalog, query, &ValidationOptions::default(), Some(row)).map(|result| result.result_set)
+        };
+        let scope = Scope::new(&[], catalog.functions()).with_subquery(&subquery);
+        let known: Vec<String> = match build_from(catalog, &select.from, scope) {
+            Ok(relation) => relation.fields.into_iter().map(|field| field.name).collect(),
+            Err(_) => catalog.tables().flat_map(|(_, table)| table.columns()).collect(),
+        };
+        let columns: HashSet<&str> = known.iter().map(String::as_str).collect();
+        let mut literals = Vec::new();
+        if let Some(selection) = &select.selection {
+            unquoted_literals(selection, &columns, &mut literals);
+        }
+        for literal in literals {
+            diagnostic = diagnostic.with_help(format!("string literals must be quoted: '{}'", literal));
+        }
+    }
+    diagnostic
+}
+
+// The columns of the sample student table
+fn student_schema() -> Schema {
+    Schema::new(vec![
+        Column::new("id", DataType::Integer).not_null(),
+        Column::new("name", DataType::Text).not_null(),
+        Column::new("major", DataType::Text).with_default(Value::from("Undeclared")),
+    ])
+}
+
+// The points a letter grade counts for towards a GPA, or NULL for anything that isn't a letter grade
+fn grade_points(args: &[Value]) -> Result<Value, QueryError> {
+    let points = match args[0].to_string().to_uppercase().as_str() {
+        "A" => 4.0,
+        "B" => 3.0,
+        "C" => 2.0,
+        "D" => 1.0,
+        "F" => 0.0,
+        _ => return Ok(Value::Null),
     };
+    Ok(Value::Float(points))
+}
+
+// The middle value of a group, or the mean of the two middle values
+struct Median(Vec<f64>);
+
+impl Accumulator for Median {
+    fn init() -> Self {
+        Median(Vec::new())
+    }
+
+    fn update(&mut self, args: &[Value]) -> Result<(), QueryError> {
+        self.0.extend(args[0].as_f64());
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<(), QueryError> {
+        self.0.extend(other.0);
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Value, QueryError> {
+        let mut values = self.0.clone();
+        values.sort_by(f64::total_cmp);
+        let middle = values.len() / 2;
+        Ok(match values.len() {
+            0 => Value::Null,
+            n if n % 2 == 0 => Value::Float((values[middle - 1] + values[middle]) / 2.0),
+            _ => Value::Float(values[middle]),
+        })
+    }
+}
+
+// The sample school database: students, the courses they can take and their enrollments, plus a
+// GRADE_POINTS function and a MEDIAN aggregate to query them with
+fn school_catalog() -> Catalog {
+    let student = Table::with_rows(
+        "student",
+        student_schema(),
+        vec![
+            hashmap! {"id".to_string() => Value::Integer(1), "name".to_string() => Value::from("Alice"), "major".to_string() => Value::from("CS")},
+            hashmap! {"id".to_string() => Value::Integer(2), "name".to_string() => Value::from("Bob"), "major".to_string() => Value::from("Math")},
+            hashmap! {"id".to_string() => Value::Integer(3), "name".to_string() => Value::from("Charlie"), "major".to_string() => Value::from("CS")},
+        ],
+    )
+    .expect("sample rows match the student schema");
+
+    let course = Table::with_rows(
+        "course",
+        Schema::new(vec![
+            Column::new("id", DataType::Integer).not_null(),
+            Column::new("title", DataType::Text).not_null(),
+            Column::new("credits", DataType::Integer).not_null(),
+        ]),
+        vec![
+            hashmap! {"id".to_string() => Value::Integer(101), "title".to_string() => Value::from("Databases"), "credits".to_string() => Value::Integer(4)},
+            hashmap! {"id".to_string() => Value::Integer(102), "title".to_string() => Value::from("Calculus"), "credits".to_string() => Value::Integer(3)},
+            hashmap! {"id".to_string() => Value::Integer(103), "title".to_string() => Value::from("Algorithms"), "credits".to_string() => Value::Integer(4)},
+            hashmap! {"id".to_string() => Value::Integer(104), "title".to_string() => Value::from("Statistics"), "credits".to_string() => Value::Integer(3)},
+        ],
+    )
+    .expect("sample rows match the course schema");
+
+    // Charlie's enrollment has not been graded yet, and nobody takes Statistics
+    let enrollment = Table::with_rows(
+        "enrollment",
+        Schema::new(vec![
+            Column::new("student_id", DataType::Integer).not_null(),
+            Column::new("course_id", DataType::Integer).not_null(),
+            Column::new("grade", DataType::Text),
+            Column::new("score", DataType::Integer),
+            Column::new("enrolled_on", DataType::Date).not_null(),
+        ]),
+        vec![
+            hashmap! {"student_id".to_string() => Value::Integer(1), "course_id".to_string() => Value::Integer(101), "grade".to_string() => Value::from("A"), "score".to_string() => Value::Integer(93), "enrolled_on".to_string() => Value::from("2024-01-15")},
+            hashmap! {"student_id".to_string() => Value::Integer(1), "course_id".to_string() => Value::Integer(103), "grade".to_string() => Value::from("B"), "score".to_string() => Value::Integer(85), "enrolled_on".to_string() => Value::from("2024-01-15")},
+            hashmap! {"student_id".to_string() => Value::Integer(2), "course_id".to_string() => Value::Integer(102), "grade".to_string() => Value::from("A"), "score".to_string() => Value::Integer(91), "enrolled_on".to_string() => Value::from("2023-09-01")},
+            hashmap! {"student_id".to_string() => Value::Integer(2), "course_id".to_string() => Value::Integer(101), "grade".to_string() => Value::from("C"), "score".to_string() => Value::Integer(72), "enrolled_on".to_string() => Value::from("2023-09-01")},
+            hashmap! {"student_id".to_string() => Value::Integer(3), "course_id".to_string() => Value::Integer(101), "enrolled_on".to_string() => Value::from("2024-01-20")},
+        ],
+    )
+    .expect("sample rows match the enrollment schema");
+
+    let mut catalog = Catalog::new();
+    catalog
+        .add_table_in("school", student)
+        .add_table_in("school", course)
+        .add_table_in("school", enrollment);
+    let grade_points_signature = Signature::new(vec![ArgType::Type(DataType::Text)], Returns::Type(DataType::Float));
+    let median_signature = Signature::new(vec![ArgType::Numeric], Returns::Type(DataType::Float));
+    catalog
+        .register_function("grade_points", grade_points_signature, grade_points)
+        .and_then(|catalog| catalog.register_aggregate::<Median>("median", median_signature))
+        .expect("the sample functions have names of their own");
+    catalog
+}
+
+fn main() {
+    let catalog = school_catalog();
+
+    // Unknown columns and HAVING without grouping reject the query unless the grader asks for them to be
+    // reported as warnings
+    let mut options = ValidationOptions::default();
+    if std::env::args().any(|arg| arg == "--warn-unknown-columns") {
+        options.unknown_columns = Severity::Warning;
+    }
+    if std::env::args().any(|arg| arg == "--allow-having-without-grouping") {
+        options.having_without_grouping = Severity::Warning;
+    }
 
     println!("Enter your SQL query:");
     print!("> ");
@@ -132,114 +899,1121 @@ fn main() {
     io::stdin().read_line(&mut sql_input).expect("Failed to read input");
     let sql_input = sql_input.trim();
 
-    let (result, is_valid) = evaluate_query(&student_table, sql_input);
-
-    println!("\nQuery Output:");
-    for row in &result {
-        println!("{:?}", row);
-    }
-
-    println!("\n{} row(s) returned.", result.len());
+    match evaluate_query_with_options(&catalog, sql_input, &options) {
+        Ok(result) => {
+            println!("\nQuery Output:");
+            println!("{}", result.result_set);
 
-    if is_valid {
-        println!("\nQuery is correct");
-    } else {
-        println!("\nQuery is incorrect");
+            println!("\n{} row(s) returned.", result.result_set.len());
+            for warning in &result.warnings {
+                println!();
+                print!("{}", diagnose(&catalog, sql_input, warning).with_severity(Severity::Warning).render(sql_input));
+            }
+            println!("\nQuery is correct");
+        }
+        Err(err) => {
+            println!("\nQuery is incorrect\n");
+            print!("{}", diagnose(&catalog, sql_input, &err).render(sql_input));
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use catalog::DEFAULT_NAMESPACE;
+    use functions::FunctionError;
+    use value::Date;
 
-    fn sample_table() -> Table {
-        Table {
-            name: "student".to_string(),
-            rows: vec![
-                hashmap! {"id".to_string() => "1".to_string(), "name".to_string() => "Alice".to_string(), "major".to_string() => "CS".to_string()},
-                hashmap! {"id".to_string() => "2".to_string(), "name".to_string() => "Bob".to_string(), "major".to_string() => "Math".to_string()},
-                hashmap! {"id".to_string() => "3".to_string(), "name".to_string() => "Charlie".to_string(), "major".to_string() => "CS".to_string()},
-            ],
-        }
+    // Evaluate a query with the default, strict, validation options
+    fn evaluate_query(catalog: &Catalog, sql: &str) -> Result<ResultSet, QueryError> {
+        evaluate_query_with_options(catalog, sql, &ValidationOptions::default()).map(|result| result.result_set)
+    }
+
+    fn sample_catalog() -> Catalog {
+        school_catalog()
+    }
+
+    // A catalog holding just one table, for tests that need their own data
+    fn catalog_of(table: Table) -> Catalog {
+        let mut catalog = Catalog::new();
+        catalog.add_table_in(DEFAULT_NAMESPACE, table);
+        catalog
     }
 
     //Unit tests are been given to validate the SQL queries
 
     #[test]
     fn test_case_1_select_star() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT * FROM student;");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM student;").unwrap();
         assert_eq!(res.len(), 3);
     }
 
     #[test]
     fn test_case_2_select_major() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT major FROM student;");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT major FROM student;").unwrap();
         assert_eq!(res.len(), 3);
-        assert!(res.iter().all(|r| r.contains_key("major")));
+        assert_eq!(res.columns, vec!["major"]);
     }
 
     #[test]
     fn test_case_3_where_major_cs() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT * FROM student WHERE major = 'CS';");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM student WHERE major = 'CS';").unwrap();
         assert_eq!(res.len(), 2);
     }
 
     #[test]
     fn test_case_4_where_major_math() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT * FROM student WHERE major = 'Math';");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM student WHERE major = 'Math';").unwrap();
         assert_eq!(res.len(), 1);
-        assert_eq!(res[0]["name"], "Bob");
+        assert_eq!(res.get(0, "name"), Some(&Value::from("Bob")));
+    }
+
+    #[test]
+    fn test_column_names_ignore_case_unless_quoted() {
+        let res = evaluate_query(&sample_catalog(), "SELECT NAME, s.Major FROM Student s WHERE MAJOR = 'Math' ORDER BY Name;")
+            .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Bob"), Value::from("Math")]]);
+        let res = evaluate_query(&sample_catalog(), "SELECT \"name\" FROM student WHERE id = 1;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Alice")]]);
+        let err = evaluate_query(&sample_catalog(), "SELECT \"NAME\" FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::UnknownColumn { column, .. } if column == "NAME"));
     }
 
     #[test]
     fn test_case_5_where_name_alice() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT id, major FROM student WHERE name = 'Alice';");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT id, major FROM student WHERE name = 'Alice';").unwrap();
         assert_eq!(res.len(), 1);
-        assert_eq!(res[0]["id"], "1");
-        assert_eq!(res[0]["major"], "CS");
+        assert_eq!(res.get(0, "id"), Some(&Value::Integer(1)));
+        assert_eq!(res.get(0, "major"), Some(&Value::from("CS")));
     }
 
     #[test]
     fn test_case_6_invalid_string_literal() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT name WHERE major = Math;");
-        assert!(!valid);
-        assert_eq!(res.len(), 0);
+        let err = evaluate_query(&sample_catalog(), "SELECT name WHERE major = Math;").unwrap_err();
+        assert_eq!(err, QueryError::MissingFrom);
+        let diagnostic = diagnose(&sample_catalog(), "SELECT name WHERE major = Math;", &err);
+        assert!(diagnostic.help.contains(&"string literals must be quoted: 'Math'".to_string()));
     }
 
     #[test]
     fn test_case_7_nonexistent_column() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT age FROM student;");
-        assert!(valid); 
-        assert_eq!(res.len(), 3);
-        assert!(res.iter().all(|r| r.is_empty()));
+        let err = evaluate_query(&sample_catalog(), "SELECT age FROM student;").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::UnknownColumn {
+                column: "age".to_string(),
+                table: "student".to_string(),
+                clause: "SELECT".to_string(),
+                suggestion: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_nonexistent_column_as_warning() {
+        let options = ValidationOptions { unknown_columns: Severity::Warning, ..ValidationOptions::default() };
+        let res = evaluate_query_with_options(&sample_catalog(), "SELECT age FROM student;", &options).unwrap();
+        assert_eq!(res.result_set.columns, vec!["age"]);
+        assert_eq!(res.result_set.rows, vec![vec![Value::Null]; 3]);
+        assert_eq!(res.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_column_in_where_suggests_closest() {
+        let err = evaluate_query(&sample_catalog(), "SELECT id FROM student WHERE mjor = 'CS';").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::UnknownColumn {
+                column: "mjor".to_string(),
+                table: "student".to_string(),
+                clause: "WHERE".to_string(),
+                suggestion: Some("major".to_string()),
+            }
+        );
     }
 
     #[test]
     fn test_case_8_missing_select_clause() {
-        let (res, valid) = evaluate_query(&sample_table(), "WHERE major = 'CS';");
-        assert!(!valid);
-        assert_eq!(res.len(), 0);
+        let err = evaluate_query(&sample_catalog(), "WHERE major = 'CS';").unwrap_err();
+        assert!(matches!(err, QueryError::Parse { line: 1, column: 1, .. }));
     }
 
     #[test]
     fn test_case_9_and_condition_match() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT * FROM student WHERE major = 'CS' AND id = '1';");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM student WHERE major = 'CS' AND id = '1';").unwrap();
         assert_eq!(res.len(), 1);
-        assert_eq!(res[0]["name"], "Alice");
+        assert_eq!(res.get(0, "name"), Some(&Value::from("Alice")));
     }
 
     #[test]
     fn test_case_10_and_condition_multiple_fields() {
-        let (res, valid) = evaluate_query(&sample_table(), "SELECT id, major FROM student WHERE name = 'Charlie' AND major = 'CS';");
-        assert!(valid);
+        let res = evaluate_query(&sample_catalog(), "SELECT id, major FROM student WHERE name = 'Charlie' AND major = 'CS';").unwrap();
         assert_eq!(res.len(), 1);
-        assert_eq!(res[0]["id"], "3");
-        assert_eq!(res[0]["major"], "CS");
+        assert_eq!(res.get(0, "id"), Some(&Value::Integer(3)));
+        assert_eq!(res.get(0, "major"), Some(&Value::from("CS")));
+    }
+
+    #[test]
+    fn test_parse_error_points_at_offending_token() {
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student\nWHERE major = = 'CS';").unwrap_err();
+        assert!(matches!(err, QueryError::Parse { line: 2, column: 15, .. }));
+    }
+
+    #[test]
+    fn test_unknown_table() {
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM teacher;").unwrap_err();
+        assert_eq!(err, QueryError::UnknownTable("teacher".to_string()));
+    }
+
+    #[test]
+    fn test_unsupported_statement() {
+        let err = evaluate_query(&sample_catalog(), "DELETE FROM student;").unwrap_err();
+        assert_eq!(err, QueryError::UnsupportedStatement("DELETE".to_string()));
+    }
+
+    #[test]
+    fn test_unsupported_expression() {
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student WHERE id = INTERVAL '1' DAY;").unwrap_err();
+        assert!(matches!(err, QueryError::UnsupportedExpression(_)));
+    }
+
+    #[test]
+    fn test_like_and_ilike() {
+        let names = |sql: &str| -> Vec<Value> {
+            let res = evaluate_query(&sample_catalog(), sql).unwrap();
+            (0..res.len()).map(|i| res.get(i, "name").unwrap().clone()).collect()
+        };
+        assert_eq!(names("SELECT name FROM student WHERE name LIKE 'A%';"), vec![Value::from("Alice")]);
+        assert_eq!(names("SELECT name FROM student WHERE name LIKE '_o_';"), vec![Value::from("Bob")]);
+        assert_eq!(names("SELECT name FROM student WHERE name NOT LIKE '%li%';"), vec![Value::from("Bob")]);
+        assert_eq!(names("SELECT name FROM student WHERE name ILIKE 'c%';"), vec![Value::from("Charlie")]);
+        assert_eq!(names("SELECT name FROM student WHERE name || '%' LIKE '%!%' ESCAPE '!';").len(), 3);
+    }
+
+    #[test]
+    fn test_similar_to_and_regex_match() {
+        let count = |sql: &str| evaluate_query(&sample_catalog(), sql).unwrap().len();
+        assert_eq!(count("SELECT name FROM student WHERE name SIMILAR TO '(A|B)%';"), 2);
+        assert_eq!(count("SELECT name FROM student WHERE name NOT SIMILAR TO '%(e|b)';"), 0);
+        assert_eq!(count("SELECT name FROM student WHERE name ~ 'li';"), 2);
+        assert_eq!(count("SELECT name FROM student WHERE name ~* '^b';"), 1);
+        assert_eq!(count("SELECT title FROM course WHERE title !~ '^[A-C]';"), 2);
+
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE name ~ '[a-';").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidPattern { .. }));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_like_requires_text() {
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student WHERE id LIKE '1%';").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::InvalidOperands {
+                operator: "LIKE".to_string(),
+                left: Some("INTEGER column 'id'".to_string()),
+                right: "'1%'".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_in_list_and_between() {
+        let count = |sql: &str| evaluate_query(&sample_catalog(), sql).unwrap().len();
+        assert_eq!(count("SELECT name FROM student WHERE major IN ('Math', 'Physics');"), 1);
+        assert_eq!(count("SELECT name FROM student WHERE id NOT IN (1, 2);"), 1);
+        // 3 NOT IN (1, NULL) is NULL, not TRUE, so no row passes
+        assert_eq!(count("SELECT name FROM student WHERE id IN (1, NULL);"), 1);
+        assert_eq!(count("SELECT name FROM student WHERE id NOT IN (1, NULL);"), 0);
+        assert_eq!(count("SELECT title FROM course WHERE credits BETWEEN 3 AND 3;"), 2);
+        assert_eq!(count("SELECT * FROM enrollment WHERE score NOT BETWEEN 80 AND 95;"), 1);
+
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student WHERE id IN (1, 'two');").unwrap_err();
+        assert!(matches!(err, QueryError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_in_subquery() {
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT name FROM student WHERE id IN (SELECT student_id FROM enrollment WHERE course_id = 103);",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Alice")]]);
+
+        let res = evaluate_query(&sample_catalog(), "SELECT title FROM course WHERE id NOT IN (SELECT course_id FROM enrollment);")
+            .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Statistics")]]);
+
+        // Charlie has no score, so NOT IN is never TRUE
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id NOT IN (SELECT score FROM enrollment);")
+            .unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_in_subquery() {
+        let err = evaluate_query(
+            &sample_catalog(),
+            "SELECT name FROM student WHERE id IN (SELECT student_id, course_id FROM enrollment);",
+        )
+        .unwrap_err();
+        assert_eq!(err, QueryError::SubqueryColumns(2));
+
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id IN (SELECT id FROM teacher);").unwrap_err();
+        assert_eq!(err, QueryError::UnknownTable("teacher".to_string()));
+    }
+
+    #[test]
+    fn test_scalar_subqueries() {
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT name, (SELECT COUNT(*) FROM enrollment e WHERE e.student_id = s.id) AS courses FROM student s ORDER BY id;",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["name", "courses"]);
+        assert_eq!(
+            res.rows,
+            vec![
+                vec![Value::from("Alice"), Value::Integer(2)],
+                vec![Value::from("Bob"), Value::Integer(2)],
+                vec![Value::from("Charlie"), Value::Integer(1)],
+            ]
+        );
+
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT student_id, course_id FROM enrollment WHERE score > (SELECT AVG(score) FROM enrollment) ORDER BY score DESC;",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(1), Value::Integer(101)], vec![Value::Integer(2), Value::Integer(102)]]);
+
+        // No rows is NULL, and the subquery can select columns of the outer row
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT (SELECT title FROM course WHERE id = 999), (SELECT s.name FROM course WHERE id = 101) FROM student s WHERE id = 2;",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Null, Value::from("Bob")]]);
+    }
+
+    #[test]
+    fn test_exists_subqueries() {
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT title FROM course c WHERE NOT EXISTS (SELECT * FROM enrollment e WHERE e.course_id = c.id);",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Statistics")]]);
+
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT name FROM student s WHERE EXISTS (SELECT * FROM enrollment WHERE student_id = s.id AND score IS NULL);",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Charlie")]]);
+    }
+
+    #[test]
+    fn test_invalid_scalar_subqueries() {
+        let err = evaluate_query(
+            &sample_catalog(),
+            "SELECT name, (SELECT course_id FROM enrollment e WHERE e.student_id = s.id) FROM student s;",
+        )
+        .unwrap_err();
+        assert_eq!(err, QueryError::SubqueryRows(2));
+
+        let err = evaluate_query(
+            &sample_catalog(),
+            "SELECT name FROM student s WHERE EXISTS (SELECT * FROM enrollment e WHERE e.student_id = s.number);",
+        )
+        .unwrap_err();
+        assert!(matches!(err, QueryError::UnknownColumn { column, .. } if column == "s.number"));
+    }
+
+    #[test]
+    fn test_derived_tables() {
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM (SELECT * FROM student) s WHERE s.major = 'Math';").unwrap();
+        assert_eq!(res.columns, vec!["id", "name", "major"]);
+        assert_eq!(res.rows, vec![vec![Value::Integer(2), Value::from("Bob"), Value::from("Math")]]);
+
+        // Column aliases rename the subquery's output columns
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT cs.n FROM (SELECT name, major FROM student WHERE major = 'CS') AS cs (n, m) ORDER BY n DESC;",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Charlie")], vec![Value::from("Alice")]]);
+
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT s.name, t.total FROM student s \
+             JOIN (SELECT student_id, SUM(score) AS total FROM enrollment GROUP BY student_id) t ON t.student_id = s.id \
+             WHERE t.total > 160 ORDER BY s.name;",
+        )
+        .unwrap();
+        assert_eq!(
+            res.rows,
+            vec![vec![Value::from("Alice"), Value::Integer(178)], vec![Value::from("Bob"), Value::Integer(163)]]
+        );
+    }
+
+    #[test]
+    fn test_invalid_derived_tables() {
+        let err = evaluate_query(&sample_catalog(), "SELECT major FROM (SELECT id, name FROM student) s;").unwrap_err();
+        assert!(matches!(err, QueryError::UnknownColumn { column, .. } if column == "major"));
+
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM (SELECT id, name FROM student) AS s (a, b, c);").unwrap_err();
+        assert!(matches!(err, QueryError::ColumnAliasCount { expected: 2, found: 3, .. }));
+
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM (SELECT id FROM teacher) t;").unwrap_err();
+        assert_eq!(err, QueryError::UnknownTable("teacher".to_string()));
+    }
+
+    #[test]
+    fn test_patterns_do_not_backtrack() {
+        let started = std::time::Instant::now();
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT name FROM student WHERE 'aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa' ~ '(a*)*b';",
+        )
+        .unwrap();
+        assert!(res.is_empty());
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE name LIKE '%a%a%a%a%a%a%a%a%a%a%a%b';")
+            .unwrap();
+        assert!(res.is_empty());
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_diagnostics_point_at_the_offending_expression() {
+        let span = |sql: &str| {
+            let err = evaluate_query(&sample_catalog(), sql).unwrap_err();
+            let span = diagnose(&sample_catalog(), sql, &err).span.unwrap();
+            (span.column, span.width)
+        };
+        // `score` is also the alias in the select list, and `name` an aggregate argument
+        assert_eq!(span("SELECT id AS score FROM student WHERE score > 3;"), (39, 5));
+        assert_eq!(span("SELECT major, MAX(name) FROM student GROUP BY major HAVING name = 'x';"), (60, 4));
+        assert_eq!(span("SELECT name AS id FROM student WHERE id = 'one';"), (38, 10));
+        assert_eq!(span("SELECT id, 10 / (id - 1) FROM student;"), (12, 13));
+        assert_eq!(span("SELECT id FROM student WHERE -id < 9223372036854775807 * id;"), (36, 24));
+    }
+
+    #[test]
+    fn test_scalar_functions() {
+        let res = evaluate_query(&sample_catalog(), "SELECT UPPER(name), LENGTH(name) FROM student WHERE id = 3;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("CHARLIE"), Value::Integer(7)]]);
+
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE SUBSTRING(name FROM 1 FOR 3) = 'Ali';").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Alice")]]);
+
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT COALESCE(grade, 'none') AS grade, CAST(score AS TEXT) FROM enrollment WHERE student_id = 3;",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("none"), Value::Null]]);
+
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT student_id, ROUND(AVG(score), 1) FROM enrollment GROUP BY student_id ORDER BY student_id;",
+        )
+        .unwrap();
+        assert_eq!(res.rows[1][1], Value::Float(81.5));
+    }
+
+    #[test]
+    fn test_function_validation() {
+        let err = evaluate_query(&sample_catalog(), "SELECT ABS(name) FROM student;").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::InvalidArguments {
+                function: "ABS".to_string(),
+                message: "expected a number for argument 1, found TEXT column 'name'".to_string(),
+            }
+        );
+
+        let err = evaluate_query(&sample_catalog(), "SELECT UPPER(name, 1) FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { message, .. } if message == "expected 1 argument, found 2"));
+
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE LENGTH(UPPER(id)) > 1;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { function, .. } if function == "UPPER"));
+
+        let err = evaluate_query(&sample_catalog(), "SELECT SUM(grade) FROM enrollment;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { function, .. } if function == "SUM"));
+
+        let sql = "SELECT uper(name) FROM student;";
+        let err = evaluate_query(&sample_catalog(), sql).unwrap_err();
+        assert_eq!(err, QueryError::UnknownFunction("UPER".to_string()));
+        let diagnostic = diagnose(&sample_catalog(), sql, &err);
+        assert_eq!(diagnostic.help, vec!["did you mean `UPPER`?"]);
+    }
+
+    #[test]
+    fn test_school_catalog_functions() {
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT student_id, MEDIAN(score), AVG(grade_points(grade)) FROM enrollment GROUP BY student_id ORDER BY 1;",
+        )
+        .unwrap();
+        assert_eq!(
+            res.rows,
+            vec![
+                vec![Value::Integer(1), Value::Float(89.0), Value::Float(3.5)],
+                vec![Value::Integer(2), Value::Float(81.5), Value::Float(3.0)],
+                vec![Value::Integer(3), Value::Null, Value::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_defined_function() {
+        let mut catalog = sample_catalog();
+        let signature = Signature::new(vec![ArgType::Numeric], Returns::Type(DataType::Text));
+        catalog
+            .register_function("grade_letter", signature, |args| {
+                let letter = match args[0].as_f64() {
+                    Some(score) if score >= 90.0 => "A",
+                    Some(score) if score >= 80.0 => "B",
+                    _ => "C",
+                };
+                Ok(Value::from(letter))
+            })
+            .unwrap();
+
+        let res = evaluate_query(
+            &catalog,
+            "SELECT score, GRADE_LETTER(score) AS letter FROM enrollment WHERE grade_letter(score) <> 'C' ORDER BY score;",
+        )
+        .unwrap();
+        assert_eq!(
+            res.rows,
+            vec![
+                vec![Value::Integer(85), Value::from("B")],
+                vec![Value::Integer(91), Value::from("A")],
+                vec![Value::Integer(93), Value::from("A")],
+            ]
+        );
+
+        let err = evaluate_query(&catalog, "SELECT grade_letter(grade) FROM enrollment;").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::InvalidArguments {
+                function: "GRADE_LETTER".to_string(),
+                message: "expected a number for argument 1, found TEXT column 'grade'".to_string(),
+            }
+        );
+        let err = evaluate_query(&catalog, "SELECT grade_letter() FROM enrollment;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { message, .. } if message == "expected 1 argument, found 0"));
+
+        // LENGTH takes TEXT, which is what grade_letter is declared to return
+        let res = evaluate_query(&catalog, "SELECT LENGTH(grade_letter(score)) FROM enrollment WHERE student_id = 1;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(1)], vec![Value::Integer(1)]]);
+        let err = evaluate_query(&catalog, "SELECT ABS(grade_letter(score)) FROM enrollment;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { function, .. } if function == "ABS"));
+    }
+
+    // Joins the distinct text values it is given with ", ", in order
+    struct NameList(Vec<String>);
+
+    impl aggregate::Accumulator for NameList {
+        fn init() -> Self {
+            NameList(Vec::new())
+        }
+
+        fn update(&mut self, args: &[Value]) -> Result<(), QueryError> {
+            self.0.push(args[0].to_string());
+            Ok(())
+        }
+
+        fn merge(&mut self, other: Self) -> Result<(), QueryError> {
+            self.0.extend(other.0);
+            Ok(())
+        }
+
+        fn finalize(&self) -> Result<Value, QueryError> {
+            let mut names = self.0.clone();
+            names.sort();
+            Ok(Value::from(names.join(", ").as_str()))
+        }
+    }
+
+    #[test]
+    fn test_user_defined_aggregate() {
+        let mut catalog = sample_catalog();
+        let signature = Signature::new(vec![ArgType::Type(DataType::Text)], Returns::Type(DataType::Text));
+        catalog.register_aggregate::<NameList>("name_list", signature).unwrap();
+
+        let res = evaluate_query(
+            &catalog,
+            "SELECT major, NAME_LIST(name) AS names FROM student GROUP BY major HAVING name_list(name) LIKE '%, %';",
+        )
+        .unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("CS"), Value::from("Alice, Charlie")]]);
+
+        let res = evaluate_query(&catalog, "SELECT name_list(DISTINCT grade), COUNT(*) FROM enrollment;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("A, B, C"), Value::Integer(5)]]);
+
+        let res = evaluate_query(
+            &catalog,
+            "SELECT name_list(name) OVER (PARTITION BY major ORDER BY id) FROM student ORDER BY id;",
+        )
+        .unwrap();
+        let names = vec![vec![Value::from("Alice")], vec![Value::from("Bob")], vec![Value::from("Alice, Charlie")]];
+        assert_eq!(res.rows, names);
+
+        let err = evaluate_query(&catalog, "SELECT name_list(score) FROM enrollment;").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::InvalidArguments {
+                function: "NAME_LIST".to_string(),
+                message: "expected TEXT for argument 1, found INTEGER column 'score'".to_string(),
+            }
+        );
+        let err = evaluate_query(&catalog, "SELECT name, name_list(major) FROM student;").unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "name".to_string(), clause: "SELECT".to_string() });
+        let err = evaluate_query(&catalog, "SELECT name FROM student WHERE name_list(major) = 'CS';").unwrap_err();
+        assert!(matches!(err, QueryError::MisplacedAggregate { clause, .. } if clause == "WHERE"));
+
+        assert_eq!(
+            catalog.register_aggregate::<NameList>("count", Signature::new(vec![ArgType::Any], Returns::FirstArgument)).err(),
+            Some(FunctionError::Reserved("COUNT".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_window_functions() {
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT name, ROW_NUMBER() OVER (PARTITION BY major ORDER BY name DESC) AS n FROM student ORDER BY major, n;",
+        )
+        .unwrap();
+        assert_eq!(
+            res.rows,
+            vec![
+                vec![Value::from("Charlie"), Value::Integer(1)],
+                vec![Value::from("Alice"), Value::Integer(2)],
+                vec![Value::from("Bob"), Value::Integer(1)],
+            ]
+        );
+
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT score, SUM(score) OVER (ORDER BY score ROWS BETWEEN 1 PRECEDING AND CURRENT ROW), \
+             LAG(score, 1, 0) OVER (ORDER BY score) FROM enrollment WHERE score IS NOT NULL ORDER BY score;",
+        )
+        .unwrap();
+        let expected = [[72, 72, 0], [85, 157, 72], [91, 176, 85], [93, 184, 91]];
+        assert_eq!(res.rows, expected.iter().map(|row| row.map(Value::Integer).to_vec()).collect::<Vec<_>>());
+
+        // Over grouped rows, ordered by an aggregate; Charlie's NULL total sorts first when descending
+        let res = evaluate_query(
+            &sample_catalog(),
+            "SELECT student_id, RANK() OVER (ORDER BY SUM(score) DESC) FROM enrollment GROUP BY student_id ORDER BY student_id;",
+        )
+        .unwrap();
+        assert_eq!(res.rows.iter().map(|row| row[1].clone()).collect::<Vec<_>>(), vec![Value::Integer(2), Value::Integer(3), Value::Integer(1)]);
+
+        // A window call only used to sort
+        let res = evaluate_query(&sample_catalog(), "SELECT title FROM course ORDER BY DENSE_RANK() OVER (ORDER BY credits), title;")
+            .unwrap();
+        assert_eq!(res.get(0, "title"), Some(&Value::from("Calculus")));
+    }
+
+    #[test]
+    fn test_invalid_window_functions() {
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE ROW_NUMBER() OVER () = 1;").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::MisplacedWindow { function: "ROW_NUMBER() OVER ()".to_string(), clause: "WHERE".to_string() }
+        );
+
+        let err = evaluate_query(&sample_catalog(), "SELECT UPPER(name) OVER () FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidWindow { function, .. } if function == "UPPER"));
+
+        let err = evaluate_query(&sample_catalog(), "SELECT LAG(score, 'one') OVER (ORDER BY score) FROM enrollment;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { function, .. } if function == "LAG"));
+
+        let err =
+            evaluate_query(&sample_catalog(), "SELECT major, ROW_NUMBER() OVER (ORDER BY name) FROM student GROUP BY major;")
+                .unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "name".to_string(), clause: "SELECT".to_string() });
+
+        let err = evaluate_query(&sample_catalog(), "SELECT SUM(RANK() OVER (ORDER BY id)) OVER () FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::MisplacedWindow { clause, .. } if clause == "another window function"));
+    }
+
+    #[test]
+    fn test_ordering_comparisons_are_numeric() {
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id >= 2;").unwrap();
+        assert_eq!(res.len(), 2);
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id < '10';").unwrap();
+        assert_eq!(res.len(), 3);
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE major <> 'CS';").unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.get(0, "name"), Some(&Value::from("Bob")));
+    }
+
+    #[test]
+    fn test_comparison_with_literal_on_the_left() {
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE 'CS' = major AND 2 < id;").unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.get(0, "name"), Some(&Value::from("Charlie")));
+    }
+
+    #[test]
+    fn test_column_to_column_comparison() {
+        let schema = Schema::new(vec![Column::new("id", DataType::Integer), Column::new("other_id", DataType::Integer)]);
+        let table = Table::with_rows(
+            "pairs",
+            schema,
+            vec![
+                hashmap! {"id".to_string() => Value::Integer(1), "other_id".to_string() => Value::Integer(1)},
+                hashmap! {"id".to_string() => Value::Integer(2), "other_id".to_string() => Value::Integer(10)},
+            ],
+        )
+        .unwrap();
+        let catalog = catalog_of(table);
+        let res = evaluate_query(&catalog, "SELECT id FROM pairs WHERE id = other_id;").unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.get(0, "id"), Some(&Value::Integer(1)));
+        let res = evaluate_query(&catalog, "SELECT id FROM pairs WHERE id < other_id;").unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.get(0, "id"), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_typed_comparisons() {
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id > 1.5;").unwrap();
+        assert_eq!(res.len(), 2);
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id = 1;").unwrap();
+        assert_eq!(res.get(0, "name"), Some(&Value::from("Alice")));
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id = 'one';").unwrap_err();
+        assert!(matches!(err, QueryError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_date_literals() {
+        let schema = Schema::new(vec![Column::new("id", DataType::Integer), Column::new("enrolled", DataType::Date)]);
+        let table = Table::with_rows(
+            "enrollment",
+            schema,
+            vec![
+                hashmap! {"id".to_string() => Value::Integer(1), "enrolled".to_string() => Value::Date(Date::parse("2023-09-01").unwrap())},
+                hashmap! {"id".to_string() => Value::Integer(2), "enrolled".to_string() => Value::Date(Date::parse("2024-01-15").unwrap())},
+            ],
+        )
+        .unwrap();
+        let catalog = catalog_of(table);
+        let res = evaluate_query(&catalog, "SELECT id FROM enrollment WHERE enrolled >= DATE '2024-01-01';").unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.get(0, "id"), Some(&Value::Integer(2)));
+        let res = evaluate_query(&catalog, "SELECT id FROM enrollment WHERE enrolled < '2023-12-31';").unwrap();
+        assert_eq!(res.get(0, "id"), Some(&Value::Integer(1)));
+        let err = evaluate_query(&catalog, "SELECT id FROM enrollment WHERE enrolled < DATE '2023-02-30';").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidLiteral { .. }));
+    }
+
+    #[test]
+    fn test_schema_drives_checks_on_empty_table() {
+        let table = Table::new("student", student_schema());
+        let catalog = catalog_of(table);
+        let res = evaluate_query(&catalog, "SELECT name FROM student WHERE major = 'CS';").unwrap();
+        assert!(res.is_empty());
+        let err = evaluate_query(&catalog, "SELECT age FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::UnknownColumn { .. }));
+        let err = evaluate_query(&catalog, "SELECT name FROM student WHERE id = 'one';").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::TypeMismatch {
+                left: "INTEGER column 'id'".to_string(),
+                right: "TEXT 'one'".to_string(),
+                expr: Some("id = 'one'".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_values_use_column_default() {
+        let mut table = Table::new("student", student_schema());
+        table.insert(hashmap! {"id".to_string() => Value::Integer(4), "name".to_string() => Value::from("Dana")}).unwrap();
+        let catalog = catalog_of(table);
+        let res = evaluate_query(&catalog, "SELECT major FROM student WHERE id = 4;").unwrap();
+        assert_eq!(res.get(0, "major"), Some(&Value::from("Undeclared")));
+    }
+
+    #[test]
+    fn test_columns_follow_projection_and_schema_order() {
+        let res = evaluate_query(&sample_catalog(), "SELECT major, id FROM student WHERE id = 1;").unwrap();
+        assert_eq!(res.columns, vec!["major", "id"]);
+        assert_eq!(res.rows, vec![vec![Value::from("CS"), Value::Integer(1)]]);
+        let res = evaluate_query(&sample_catalog(), "SELECT *, id FROM student;").unwrap();
+        assert_eq!(res.columns, vec!["id", "name", "major", "id"]);
+        assert_eq!(res.rows[2], vec![Value::Integer(3), Value::from("Charlie"), Value::from("CS"), Value::Integer(3)]);
+    }
+
+    #[test]
+    fn test_catalog_resolves_qualified_and_bare_names() {
+        let res = evaluate_query(&sample_catalog(), "SELECT title FROM school.course WHERE credits = 4;").unwrap();
+        assert_eq!(res.len(), 2);
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM enrollment WHERE student_id = 2;").unwrap();
+        assert_eq!(res.columns, vec!["student_id", "course_id", "grade", "score", "enrolled_on"]);
+        assert_eq!(res.len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_table_suggests_catalog_table() {
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM students;").unwrap_err();
+        let diagnostic = diagnose(&sample_catalog(), "SELECT * FROM students;", &err);
+        assert_eq!(diagnostic.help, vec!["did you mean `school.student`?"]);
+    }
+
+    #[test]
+    fn test_inner_join_with_aliases() {
+        let sql = "SELECT s.name, c.title, e.grade FROM student AS s \
+                   JOIN enrollment e ON e.student_id = s.id \
+                   INNER JOIN course c ON c.id = e.course_id \
+                   WHERE c.credits = 4 AND s.major = 'CS';";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["name", "title", "grade"]);
+        assert_eq!(res.len(), 3);
+        assert_eq!(res.rows[1], vec![Value::from("Alice"), Value::from("Algorithms"), Value::from("B")]);
+        assert_eq!(res.get(2, "name"), Some(&Value::from("Charlie")));
+        assert_eq!(res.get(2, "grade"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn test_outer_joins_pad_with_nulls() {
+        let sql = "SELECT title, student_id FROM course LEFT JOIN enrollment ON course_id = id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.len(), 6);
+        assert_eq!(res.rows[5], vec![Value::from("Statistics"), Value::Null]);
+
+        let sql = "SELECT c.id, s.id FROM course c RIGHT JOIN student s ON c.credits = s.id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.len(), 4);
+        assert_eq!(res.rows[3], vec![Value::Null, Value::Integer(2)]);
+
+        let sql = "SELECT c.id, s.id FROM course c FULL OUTER JOIN student s ON c.credits = s.id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.len(), 6);
+        assert_eq!(res.rows[5], vec![Value::Null, Value::Integer(2)]);
+    }
+
+    #[test]
+    fn test_using_and_natural_joins_merge_columns() {
+        let sql = "SELECT * FROM student JOIN enrollment AS e (id, course_id) USING (id) WHERE course_id = 102;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["id", "name", "major", "course_id", "grade", "score", "enrolled_on"]);
+        assert_eq!(res.rows[0][..2], [Value::Integer(2), Value::from("Bob")]);
+
+        let sql = "SELECT s.*, e.id FROM student s NATURAL LEFT JOIN enrollment AS e (id) WHERE name = 'Charlie';";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["id", "name", "major", "id"]);
+        assert_eq!(res.rows, vec![vec![Value::Integer(3), Value::from("Charlie"), Value::from("CS"), Value::Integer(3)]]);
+    }
+
+    #[test]
+    fn test_comma_and_cross_joins() {
+        let res = evaluate_query(&sample_catalog(), "SELECT * FROM student, course;").unwrap();
+        assert_eq!(res.len(), 12);
+        let sql = "SELECT name FROM student CROSS JOIN course WHERE title = 'Calculus' AND student.id = 2;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Bob")]]);
+    }
+
+    #[test]
+    fn test_join_column_errors() {
+        let err = evaluate_query(&sample_catalog(), "SELECT id FROM student, course;").unwrap_err();
+        assert_eq!(err, QueryError::AmbiguousColumn("id".to_string()));
+        let err = evaluate_query(&sample_catalog(), "SELECT s.title FROM student s, course c;").unwrap_err();
+        assert!(matches!(err, QueryError::UnknownColumn { table, .. } if table == "s"));
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student s (a, b, c, d);").unwrap_err();
+        assert!(matches!(err, QueryError::ColumnAliasCount { expected: 3, found: 4, .. }));
+    }
+
+    #[test]
+    fn test_group_by_with_aggregates() {
+        let sql = "SELECT major, COUNT(*), MIN(name), MAX(id) FROM student GROUP BY major;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["major", "COUNT(*)", "MIN(name)", "MAX(id)"]);
+        assert_eq!(res.rows[0], vec![Value::from("CS"), Value::Integer(2), Value::from("Alice"), Value::Integer(3)]);
+        assert_eq!(res.rows[1], vec![Value::from("Math"), Value::Integer(1), Value::from("Bob"), Value::Integer(2)]);
+
+        let sql = "SELECT e.course_id, COUNT(score), SUM(score), AVG(score) FROM enrollment e GROUP BY e.course_id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res.rows[0], vec![Value::Integer(101), Value::Integer(2), Value::Integer(165), Value::Float(82.5)]);
+    }
+
+    #[test]
+    fn test_aggregates_without_group_by() {
+        let sql = "SELECT COUNT(*), COUNT(DISTINCT major), COUNT(grade) FROM student JOIN enrollment ON student_id = id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(5), Value::Integer(2), Value::Integer(4)]]);
+
+        // Aggregating no rows still produces one row
+        let res = evaluate_query(&sample_catalog(), "SELECT COUNT(*), SUM(id) FROM student WHERE id > 10;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(0), Value::Null]]);
+    }
+
+    #[test]
+    fn test_group_by_position_and_alias() {
+        let expected = vec![vec![Value::from("CS"), Value::Integer(2)], vec![Value::from("Math"), Value::Integer(1)]];
+        let res = evaluate_query(&sample_catalog(), "SELECT major, COUNT(*) FROM student GROUP BY 1 ORDER BY 1;").unwrap();
+        assert_eq!(res.rows, expected);
+        let res = evaluate_query(&sample_catalog(), "SELECT major AS m, COUNT(*) FROM student GROUP BY m ORDER BY m;").unwrap();
+        assert_eq!(res.rows, expected);
+
+        // A column of FROM wins over an alias of the same name
+        let sql = "SELECT major AS name, COUNT(*) FROM student GROUP BY name;";
+        let err = evaluate_query(&sample_catalog(), sql).unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "major".to_string(), clause: "SELECT".to_string() });
+
+        let err = evaluate_query(&sample_catalog(), "SELECT major, COUNT(*) FROM student GROUP BY 3;").unwrap_err();
+        assert_eq!(err, QueryError::InvalidOrdinal { position: "3".to_string(), count: 2 });
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student GROUP BY 1;").unwrap_err();
+        assert_eq!(err, QueryError::UnsupportedExpression("GROUP BY 1".to_string()));
+        let err = evaluate_query(&sample_catalog(), "SELECT COUNT(*) AS c FROM student GROUP BY c;").unwrap_err();
+        assert!(matches!(err, QueryError::MisplacedAggregate { clause, .. } if clause == "GROUP BY"));
+    }
+
+    #[test]
+    fn test_ungrouped_columns_are_rejected() {
+        let err = evaluate_query(&sample_catalog(), "SELECT name, COUNT(*) FROM student GROUP BY major;").unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "name".to_string(), clause: "SELECT".to_string() });
+        let err = evaluate_query(&sample_catalog(), "SELECT id, MAX(id) FROM student;").unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "id".to_string(), clause: "SELECT".to_string() });
+        let err = evaluate_query(&sample_catalog(), "SELECT * FROM student GROUP BY id;").unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "*".to_string(), clause: "SELECT".to_string() });
+        let err = evaluate_query(&sample_catalog(), "SELECT SUM(name) FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidArguments { function, .. } if function == "SUM"));
+    }
+
+    #[test]
+    fn test_having_filters_groups() {
+        let sql = "SELECT major, COUNT(*) FROM student GROUP BY major HAVING COUNT(*) > 1;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("CS"), Value::Integer(2)]]);
+
+        // HAVING can use aggregates that are not projected, and grouped columns
+        let sql = "SELECT course_id FROM enrollment GROUP BY course_id HAVING AVG(score) >= 80 AND course_id <> 103;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(101)], vec![Value::Integer(102)]]);
+
+        // Without GROUP BY, HAVING filters the single group of all rows
+        let res = evaluate_query(&sample_catalog(), "SELECT MAX(id) FROM student HAVING COUNT(*) > 5;").unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn test_having_and_aggregate_placement_errors() {
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student HAVING id > 1;").unwrap_err();
+        assert_eq!(err, QueryError::HavingWithoutGrouping);
+        // Dialects that allow it filter the rows with it instead
+        let options = ValidationOptions { having_without_grouping: Severity::Warning, ..ValidationOptions::default() };
+        let sql = "SELECT name FROM student WHERE major = 'CS' HAVING id > 1;";
+        let res = evaluate_query_with_options(&sample_catalog(), sql, &options).unwrap();
+        assert_eq!(res.result_set.rows, vec![vec![Value::from("Charlie")]]);
+        assert_eq!(res.warnings, vec![QueryError::HavingWithoutGrouping]);
+        let err = evaluate_query(&sample_catalog(), "SELECT major FROM student GROUP BY major HAVING id > 1;").unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "id".to_string(), clause: "HAVING".to_string() });
+        let err = evaluate_query(&sample_catalog(), "SELECT major FROM student WHERE COUNT(*) > 1;").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::MisplacedAggregate { aggregate: "COUNT(*)".to_string(), clause: "WHERE".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_order_by_keys_and_directions() {
+        let res = evaluate_query(&sample_catalog(), "SELECT name FROM student ORDER BY major DESC, id DESC;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Bob")], vec![Value::from("Charlie")], vec![Value::from("Alice")]]);
+
+        // Ordinals and aliases name output columns; other keys are evaluated against the rows
+        let sql = "SELECT title, credits AS c FROM course ORDER BY c, 1 DESC;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        let titles: Vec<&Value> = (0..res.len()).map(|i| res.get(i, "title").unwrap()).collect();
+        assert_eq!(titles, vec![&Value::from("Statistics"), &Value::from("Calculus"), &Value::from("Databases"), &Value::from("Algorithms")]);
+        assert_eq!(res.columns, vec!["title", "c"]);
+    }
+
+    #[test]
+    fn test_order_by_sorts_numbers_and_nulls() {
+        let sql = "SELECT student_id, score FROM enrollment ORDER BY score;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        let scores: Vec<&Value> = (0..res.len()).map(|i| res.get(i, "score").unwrap()).collect();
+        assert_eq!(scores, vec![&Value::Integer(72), &Value::Integer(85), &Value::Integer(91), &Value::Integer(93), &Value::Null]);
+
+        let sql = "SELECT score FROM enrollment ORDER BY score DESC NULLS LAST;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows[0], vec![Value::Integer(93)]);
+        assert_eq!(res.rows[4], vec![Value::Null]);
+
+        let sql = "SELECT major, COUNT(*) AS n FROM student GROUP BY major ORDER BY COUNT(*), major;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Math"), Value::Integer(1)], vec![Value::from("CS"), Value::Integer(2)]]);
+    }
+
+    #[test]
+    fn test_order_by_errors() {
+        let err = evaluate_query(&sample_catalog(), "SELECT id, name FROM student ORDER BY 3;").unwrap_err();
+        assert_eq!(err, QueryError::InvalidOrdinal { position: "3".to_string(), count: 2 });
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student ORDER BY age;").unwrap_err();
+        assert!(matches!(err, QueryError::UnknownColumn { column, .. } if column == "age"));
+        let err = evaluate_query(&sample_catalog(), "SELECT major FROM student GROUP BY major ORDER BY name;").unwrap_err();
+        assert_eq!(err, QueryError::NotGrouped { column: "name".to_string(), clause: "ORDER BY".to_string() });
+        let sql = "SELECT s.id, c.id FROM student s, course c ORDER BY id;";
+        assert_eq!(evaluate_query(&sample_catalog(), sql).unwrap_err(), QueryError::AmbiguousColumn("id".to_string()));
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let res = evaluate_query(&sample_catalog(), "SELECT id FROM course ORDER BY id DESC LIMIT 2;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(104)], vec![Value::Integer(103)]]);
+        let res = evaluate_query(&sample_catalog(), "SELECT id FROM course ORDER BY id LIMIT 2 OFFSET 3;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(104)]]);
+        let res = evaluate_query(&sample_catalog(), "SELECT id FROM course OFFSET 10;").unwrap();
+        assert!(res.is_empty());
+        let res = evaluate_query(&sample_catalog(), "SELECT id FROM course LIMIT 0;").unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn test_fetch_first_with_ties() {
+        let sql = "SELECT title FROM course ORDER BY credits OFFSET 1 ROWS FETCH FIRST 1 ROWS ONLY;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Statistics")]]);
+
+        let sql = "SELECT title FROM course ORDER BY credits DESC FETCH FIRST 1 ROWS WITH TIES;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Databases")], vec![Value::from("Algorithms")]]);
+
+        let err = evaluate_query(&sample_catalog(), "SELECT title FROM course FETCH FIRST 1 ROWS WITH TIES;").unwrap_err();
+        assert_eq!(err, QueryError::TiesWithoutOrderBy);
+    }
+
+    #[test]
+    fn test_limit_parameters_and_validation() {
+        let options = ValidationOptions { parameters: vec![Value::Integer(1), Value::Integer(2)], ..ValidationOptions::default() };
+        let sql = "SELECT id FROM course ORDER BY id LIMIT $2 OFFSET $1;";
+        let res = evaluate_query_with_options(&sample_catalog(), sql, &options).unwrap().result_set;
+        assert_eq!(res.rows, vec![vec![Value::Integer(102)], vec![Value::Integer(103)]]);
+        let sql = "SELECT id FROM course ORDER BY id LIMIT ? OFFSET ?;";
+        let res = evaluate_query_with_options(&sample_catalog(), sql, &options).unwrap().result_set;
+        assert_eq!(res.rows, vec![vec![Value::Integer(103)]]);
+
+        let err = evaluate_query(&sample_catalog(), "SELECT id FROM course LIMIT $1;").unwrap_err();
+        assert_eq!(err, QueryError::UnboundParameter("$1".to_string()));
+        let err = evaluate_query(&sample_catalog(), "SELECT id FROM course LIMIT -1;").unwrap_err();
+        assert_eq!(err, QueryError::InvalidLimit { clause: "LIMIT".to_string(), value: "-1".to_string() });
+        let err = evaluate_query(&sample_catalog(), "SELECT id FROM course LIMIT 1.5;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidLimit { .. }));
+        let err = evaluate_query(&sample_catalog(), "SELECT id FROM course OFFSET id;").unwrap_err();
+        assert_eq!(err, QueryError::InvalidLimit { clause: "OFFSET".to_string(), value: "id".to_string() });
+    }
+
+    #[test]
+    fn test_select_distinct() {
+        let res = evaluate_query(&sample_catalog(), "SELECT DISTINCT major FROM student;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("CS")], vec![Value::from("Math")]]);
+
+        // NULLs count as duplicates of each other, and DISTINCT applies before LIMIT
+        let sql = "SELECT DISTINCT grade FROM enrollment ORDER BY grade NULLS FIRST LIMIT 3;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Null], vec![Value::from("A")], vec![Value::from("B")]]);
+
+        let err = evaluate_query(&sample_catalog(), "SELECT DISTINCT major FROM student ORDER BY name;").unwrap_err();
+        assert_eq!(err, QueryError::OrderByNotInDistinct("name".to_string()));
+    }
+
+    #[test]
+    fn test_distinct_on_keeps_first_row_per_key() {
+        let sql = "SELECT DISTINCT ON (major) major, name FROM student ORDER BY major, id DESC;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(
+            res.rows,
+            vec![vec![Value::from("CS"), Value::from("Charlie")], vec![Value::from("Math"), Value::from("Bob")]]
+        );
+
+        // The best score per course, where the key is not projected
+        let sql = "SELECT DISTINCT ON (course_id) student_id, score FROM enrollment ORDER BY course_id, score DESC NULLS LAST;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res.rows[0], vec![Value::Integer(1), Value::Integer(93)]);
+
+        let sql = "SELECT DISTINCT ON (major) name FROM student ORDER BY name;";
+        let err = evaluate_query(&sample_catalog(), sql).unwrap_err();
+        assert_eq!(err, QueryError::DistinctOnOrder("name".to_string()));
+    }
+
+    #[test]
+    fn test_computed_projection_and_aliases() {
+        let sql = "SELECT id + 1, UPPER(name) AS upper_name, 'x' || major FROM student WHERE id = 2;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["id + 1", "upper_name", "'x' || major"]);
+        assert_eq!(res.rows, vec![vec![Value::Integer(3), Value::from("BOB"), Value::from("xMath")]]);
+
+        let sql = "SELECT s.name AS student, score * 1.0 / 100 AS ratio FROM student s JOIN enrollment ON id = student_id \
+                   ORDER BY ratio DESC NULLS LAST LIMIT 1;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["student", "ratio"]);
+        assert_eq!(res.rows, vec![vec![Value::from("Alice"), Value::Float(0.93)]]);
+    }
+
+    #[test]
+    fn test_computed_projection_over_groups() {
+        let sql = "SELECT major || ':' AS label, COUNT(*) * 10 FROM student GROUP BY major ORDER BY 2;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["label", "COUNT(*) * 10"]);
+        assert_eq!(res.rows[1], vec![Value::from("CS:"), Value::Integer(20)]);
+        let err = evaluate_query(&sample_catalog(), "SELECT id / 0 FROM student;").unwrap_err();
+        assert_eq!(err, QueryError::DivisionByZero { expr: Some("id / 0".to_string()) });
+        let err = evaluate_query(&sample_catalog(), "SELECT name * 2 FROM student;").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidOperands { .. }));
+    }
+
+    #[test]
+    fn test_general_expressions_in_every_clause() {
+        let sql = "SELECT name FROM student WHERE NOT (major = 'CS' AND id > 1) ORDER BY -id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("Bob")], vec![Value::from("Alice")]]);
+
+        let sql = "SELECT title, CASE WHEN credits >= 4 THEN 'core' ELSE 'elective' END AS kind \
+                   FROM course WHERE (id - 100) * 2 <= 4 ORDER BY id;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.columns, vec!["title", "kind"]);
+        assert_eq!(res.rows[1], vec![Value::from("Calculus"), Value::from("elective")]);
+
+        let sql = "SELECT course_id FROM enrollment GROUP BY course_id HAVING MAX(score) - MIN(score) > 10;";
+        let res = evaluate_query(&sample_catalog(), sql).unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(101)]]);
+    }
+
+    #[test]
+    fn test_non_boolean_condition_is_an_error() {
+        let err = evaluate_query(&sample_catalog(), "SELECT name FROM student WHERE id + 1;").unwrap_err();
+        assert_eq!(err, QueryError::NotACondition { expr: "id + 1".to_string(), found: "INTEGER 2".to_string() });
+    }
+
+    #[test]
+    fn test_null_predicates_in_where() {
+        let res = evaluate_query(&sample_catalog(), "SELECT student_id FROM enrollment WHERE grade IS NULL;").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::Integer(3)]]);
+        let res = evaluate_query(&sample_catalog(), "SELECT student_id FROM enrollment WHERE grade IS NOT NULL;").unwrap();
+        assert_eq!(res.len(), 4);
+
+        // Comparing with NULL is UNKNOWN, so the row is dropped whichever way the comparison goes
+        let res = evaluate_query(&sample_catalog(), "SELECT grade FROM enrollment WHERE grade <> 'A';").unwrap();
+        assert_eq!(res.rows, vec![vec![Value::from("B")], vec![Value::from("C")]]);
+        let res = evaluate_query(&sample_catalog(), "SELECT grade FROM enrollment WHERE NOT (grade = 'A');").unwrap();
+        assert_eq!(res.len(), 2);
+        let sql = "SELECT grade FROM enrollment WHERE grade IS DISTINCT FROM 'A';";
+        assert_eq!(evaluate_query(&sample_catalog(), sql).unwrap().len(), 3);
+
+        // ... but OR can still be TRUE
+        let sql = "SELECT student_id FROM enrollment WHERE score > 90 OR course_id = 101;";
+        assert_eq!(evaluate_query(&sample_catalog(), sql).unwrap().len(), 4);
+    }
+}
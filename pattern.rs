@@ -0,0 +1,486 @@
+use crate::error::QueryError;
+
+// A compiled LIKE, SIMILAR TO or POSIX regex pattern. All three parse into the same pieces (literals, any
+// character, bracket classes, groups with alternation, repetition and anchors), which are compiled into a
+// small state machine. Matching follows every state at once, one character at a time, so it takes time
+// proportional to the text times the pattern and never backtracks.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    states: Vec<State>,
+    start: usize,
+    case_insensitive: bool,
+    // LIKE and SIMILAR TO have to match the whole text, a regex may match anywhere in it
+    anchored: bool,
+}
+
+// One node repeated between min and max (unbounded when None) times
+#[derive(Debug, Clone)]
+struct Piece {
+    node: Node,
+    min: usize,
+    max: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+    Start,
+    End,
+    Group(Vec<Vec<Piece>>),
+}
+
+// A state of the compiled pattern, naming the state(s) that come after it. Only Step consumes a character;
+// the others are followed as soon as they are reached.
+#[derive(Debug, Clone)]
+enum State {
+    Step(Node, usize),
+    Split(Vec<usize>),
+    Start(usize),
+    End(usize),
+    Match,
+}
+
+// Counted repetitions are compiled by copying the repeated node, so `(a{1000}){1000}` would need a million
+// states; patterns that large are rejected
+const MAX_STATES: usize = 10_000;
+
+fn invalid(pattern: &str, message: &str) -> QueryError {
+    QueryError::InvalidPattern { pattern: pattern.to_string(), message: message.to_string() }
+}
+
+impl Pattern {
+    fn compile(
+        pattern: &str,
+        alternatives: &[Vec<Piece>],
+        case_insensitive: bool,
+        anchored: bool,
+    ) -> Result<Self, QueryError> {
+        let mut compiler = Compiler { states: Vec::new(), pattern };
+        let accept = compiler.push(State::Match)?;
+        let start = compiler.alternatives(alternatives, accept)?;
+        Ok(Pattern { states: compiler.states, start, case_insensitive, anchored })
+    }
+
+    // `%` matches any run of characters and `_` any single one; the escape character (a backslash unless
+    // ESCAPE says otherwise) makes the character after it literal
+    pub fn like(pattern: &str, escape: Option<char>, case_insensitive: bool) -> Result<Self, QueryError> {
+        let escape = escape.unwrap_or('\\');
+        let mut pieces = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let (node, min, max) = match c {
+                _ if c == escape => match chars.next() {
+                    Some(escaped) => (Node::Char(escaped), 1, Some(1)),
+                    None => return Err(invalid(pattern, "pattern must not end with the escape character")),
+                },
+                '%' => (Node::Any, 0, None),
+                '_' => (Node::Any, 1, Some(1)),
+                c => (Node::Char(c), 1, Some(1)),
+            };
+            pieces.push(Piece { node, min, max });
+        }
+        Pattern::compile(pattern, &[pieces], case_insensitive, true)
+    }
+
+    // SIMILAR TO is a regex with LIKE's wildcards that has to match the whole text. It is translated
+    // into regex syntax: `%` and `_` become `.*` and `.`, while `.`, `^` and `$` are plain characters. Like
+    // PostgreSQL, an escaped character is passed on with a backslash, so `\d` still means a digit.
+    pub fn similar_to(pattern: &str, escape: Option<char>) -> Result<Self, QueryError> {
+        let escape = escape.unwrap_or('\\');
+        let mut regex = String::new();
+        let mut in_class = false;
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                _ if c == escape => match chars.next() {
+                    Some(escaped) => {
+                        regex.push('\\');
+                        regex.push(escaped);
+                    }
+                    None => return Err(invalid(pattern, "pattern must not end with the escape character")),
+                },
+                '\\' => regex.push_str("\\\\"),
+                ']' if in_class => {
+                    in_class = false;
+                    regex.push(c);
+                }
+                _ if in_class => regex.push(c),
+                '[' => {
+                    in_class = true;
+                    regex.push(c);
+                }
+                '%' => regex.push_str(".*"),
+                '_' => regex.push('.'),
+                '.' | '^' | '$' => {
+                    regex.push('\\');
+                    regex.push(c);
+                }
+                c => regex.push(c),
+            }
+        }
+        let alternatives = RegexParser::new(&regex, pattern).parse()?;
+        Pattern::compile(pattern, &alternatives, false, true)
+    }
+
+    // A POSIX-style regex as used by `~` and `~*`: . [] [^] () | * + ? {m,n} ^ $ and the \d \w \s classes
+    pub fn regex(pattern: &str, case_insensitive: bool) -> Result<Self, QueryError> {
+        let alternatives = RegexParser::new(pattern, pattern).parse()?;
+        Pattern::compile(pattern, &alternatives, case_insensitive, false)
+    }
+
+    // Keep the set of states reached after each character. An unanchored pattern may also start at every
+    // position, and matches as soon as any state accepts.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let mut seen = vec![usize::MAX; self.states.len()];
+        let mut current = Vec::new();
+        let mut matched = self.follow(self.start, 0, &text, &mut current, &mut seen);
+        for (pos, &c) in text.iter().enumerate() {
+            if matched && !self.anchored {
+                return true;
+            }
+            let mut next = Vec::new();
+            matched = false;
+            for &state in &current {
+                if let State::Step(node, to) = &self.states[state]
+                    && self.matches(node, c)
+                {
+                    matched |= self.follow(*to, pos + 1, &text, &mut next, &mut seen);
+                }
+            }
+            if !self.anchored {
+                matched |= self.follow(self.start, pos + 1, &text, &mut next, &mut seen);
+            }
+            current = next;
+        }
+        matched
+    }
+
+    // Add the states reachable from `state` at `pos` without consuming a character to `steps`, keeping the
+    // ones that wait for a character. `seen` holds the position each state was last added at, so every state
+    // is visited once per position. Returns whether the pattern accepts there.
+    fn follow(&self, state: usize, pos: usize, text: &[char], steps: &mut Vec<usize>, seen: &mut [usize]) -> bool {
+        let mut accepted = false;
+        let mut pending = vec![state];
+        while let Some(state) = pending.pop() {
+            if seen[state] == pos {
+                continue;
+            }
+            seen[state] = pos;
+            match &self.states[state] {
+                State::Step(..) => steps.push(state),
+                State::Split(targets) => pending.extend(targets.iter().rev()),
+                State::Start(next) if pos == 0 => pending.push(*next),
+                State::End(next) if pos == text.len() => pending.push(*next),
+                State::Start(_) | State::End(_) => {}
+                State::Match => accepted = true,
+            }
+        }
+        accepted
+    }
+
+    fn matches(&self, node: &Node, c: char) -> bool {
+        let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+        match node {
+            Node::Any => true,
+            Node::Char(expected) => *expected == c || self.case_insensitive && lower(*expected) == lower(c),
+            Node::Class { negated, ranges } => {
+                let contains = |c: char| ranges.iter().any(|&(low, high)| low <= c && c <= high);
+                let upper = c.to_uppercase().next().unwrap_or(c);
+                let found = contains(c) || self.case_insensitive && (contains(lower(c)) || contains(upper));
+                found != *negated
+            }
+            _ => false,
+        }
+    }
+}
+
+// Builds the states back to front: each piece is compiled knowing the state that follows it
+struct Compiler<'a> {
+    states: Vec<State>,
+    pattern: &'a str,
+}
+
+impl Compiler<'_> {
+    fn push(&mut self, state: State) -> Result<usize, QueryError> {
+        if self.states.len() >= MAX_STATES {
+            return Err(invalid(self.pattern, "pattern is too complex"));
+        }
+        self.states.push(state);
+        Ok(self.states.len() - 1)
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Piece>], next: usize) -> Result<usize, QueryError> {
+        match alternatives {
+            [pieces] => self.sequence(pieces, next),
+            _ => {
+                let targets =
+                    alternatives.iter().map(|pieces| self.sequence(pieces, next)).collect::<Result<Vec<_>, _>>()?;
+                self.push(State::Split(targets))
+            }
+        }
+    }
+
+    fn sequence(&mut self, pieces: &[Piece], next: usize) -> Result<usize, QueryError> {
+        pieces.iter().rev().try_fold(next, |next, piece| self.piece(piece, next))
+    }
+
+    // `x{2,4}` becomes `x x (x (x)?)?` and `x{2,}` becomes `x x` followed by a loop back over `x`
+    fn piece(&mut self, piece: &Piece, next: usize) -> Result<usize, QueryError> {
+        let mut entry = match piece.max {
+            None => {
+                let repeat = self.push(State::Split(Vec::new()))?;
+                let body = self.node(&piece.node, repeat)?;
+                self.states[repeat] = State::Split(vec![body, next]);
+                repeat
+            }
+            Some(max) => {
+                let mut entry = next;
+                for _ in piece.min..max {
+                    let body = self.node(&piece.node, entry)?;
+                    entry = self.push(State::Split(vec![body, entry]))?;
+                }
+                entry
+            }
+        };
+        for _ in 0..piece.min {
+            entry = self.node(&piece.node, entry)?;
+        }
+        Ok(entry)
+    }
+
+    fn node(&mut self, node: &Node, next: usize) -> Result<usize, QueryError> {
+        match node {
+            Node::Start => self.push(State::Start(next)),
+            Node::End => self.push(State::End(next)),
+            Node::Group(alternatives) => self.alternatives(alternatives, next),
+            _ => self.push(State::Step(node.clone(), next)),
+        }
+    }
+}
+
+// Recursive descent over the regex syntax; errors name the pattern as the user wrote it, which for
+// SIMILAR TO is not the translated regex
+struct RegexParser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    original: &'a str,
+}
+
+impl<'a> RegexParser<'a> {
+    fn new(regex: &str, original: &'a str) -> Self {
+        RegexParser { chars: regex.chars().collect(), pos: 0, original }
+    }
+
+    fn parse(mut self) -> Result<Vec<Vec<Piece>>, QueryError> {
+        let alternatives = self.alternatives()?;
+        match self.peek() {
+            Some(_) => Err(self.error("unbalanced parentheses")),
+            None => Ok(alternatives),
+        }
+    }
+
+    fn error(&self, message: &str) -> QueryError {
+        invalid(self.original, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += usize::from(c.is_some());
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Piece>>, QueryError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Piece>, QueryError> {
+        let mut pieces = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.pos += 1;
+            let node = match c {
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '(' => {
+                    let group = self.alternatives()?;
+                    if !self.eat(')') {
+                        return Err(self.error("unbalanced parentheses"));
+                    }
+                    Node::Group(group)
+                }
+                '[' => self.class()?,
+                '\\' => match self.next() {
+                    Some(escaped) => escape_class(escaped).unwrap_or(Node::Char(escaped)),
+                    None => return Err(self.error("pattern must not end with a backslash")),
+                },
+                '*' | '+' | '?' | '{' => return Err(self.error(&format!("nothing to repeat before '{}'", c))),
+                c => Node::Char(c),
+            };
+            let (min, max) = self.repetition()?;
+            pieces.push(Piece { node, min, max });
+        }
+        Ok(pieces)
+    }
+
+    // The quantifier after a node, if any. Lazy quantifiers such as `*?` are accepted, but since only
+    // whether the text matches is asked, they behave like greedy ones.
+    fn repetition(&mut self) -> Result<(usize, Option<usize>), QueryError> {
+        let bounds = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number().ok_or_else(|| self.error("invalid repetition count"))?;
+                let max = if self.eat(',') { self.number() } else { Some(min) };
+                if self.peek() != Some('}') || max.is_some_and(|max| max < min) {
+                    return Err(self.error("invalid repetition count"));
+                }
+                (min, max)
+            }
+            _ => return Ok((1, Some(1))),
+        };
+        self.pos += 1;
+        self.eat('?');
+        Ok(bounds)
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    // A bracket expression such as [a-z_] or [^0-9]; a `]` right after the opening bracket is literal
+    fn class(&mut self) -> Result<Node, QueryError> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or_else(|| self.error("unterminated bracket expression"))?;
+            let low = match c {
+                ']' if !first => break,
+                '\\' => {
+                    let escaped = self.next().ok_or_else(|| self.error("unterminated bracket expression"))?;
+                    if let Some(Node::Class { negated: false, ranges: class }) = escape_class(escaped) {
+                        ranges.extend(class);
+                        first = false;
+                        continue;
+                    }
+                    escaped
+                }
+                c => c,
+            };
+            first = false;
+            let high = if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']') {
+                self.pos += 1;
+                self.next().unwrap_or(low)
+            } else {
+                low
+            };
+            if high < low {
+                return Err(self.error(&format!("invalid range {}-{}", low, high)));
+            }
+            ranges.push((low, high));
+        }
+        Ok(Node::Class { negated, ranges })
+    }
+}
+
+// The \d, \w and \s shorthands and their negated upper-case forms
+fn escape_class(c: char) -> Option<Node> {
+    let ranges = match c.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![(' ', ' '), ('\t', '\r')],
+        _ => return None,
+    };
+    Some(Node::Class { negated: c.is_ascii_uppercase(), ranges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn like(text: &str, pattern: &str) -> bool {
+        Pattern::like(pattern, None, false).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn test_like_and_similar_to() {
+        assert!(like("Alice", "A%"));
+        assert!(like("Bob", "_o_"));
+        assert!(!like("Bob", "_o"));
+        assert!(like("", "%"));
+        assert!(like("a%b", "a\\%b"));
+        assert!(!like("axb", "a\\%b"));
+        assert!(Pattern::like("50!%", Some('!'), false).unwrap().is_match("50%"));
+        assert!(Pattern::like("c%", None, true).unwrap().is_match("Charlie"));
+        assert!(matches!(Pattern::like("abc\\", None, false), Err(QueryError::InvalidPattern { .. })));
+
+        let similar = |text: &str, pattern: &str| Pattern::similar_to(pattern, None).unwrap().is_match(text);
+        assert!(similar("Bob", "(A|B)%"));
+        assert!(!similar("Charlie", "(A|B)%"));
+        assert!(similar("abc.d", "[a-c]+.d"));
+        assert!(!similar("abcxd", "[a-c]+.d"));
+        assert!(similar("2024", "\\d{4}"));
+        let escaped = Pattern::similar_to("a#_b", Some('#')).unwrap();
+        assert!(escaped.is_match("a_b") && !escaped.is_match("axb"));
+    }
+
+    #[test]
+    fn test_regex() {
+        let regex = |text: &str, pattern: &str| Pattern::regex(pattern, false).unwrap().is_match(text);
+        assert!(regex("Charlie", "li"));
+        assert!(regex("Charlie", "^Ch.*e$"));
+        assert!(!regex("Charlie", "^li"));
+        assert!(regex("id-42", "\\d{2,3}$"));
+        assert!(regex("aaa", "^(a|b)*$"));
+        assert!(!regex("abc", "[^a-c]"));
+        assert!(regex("x]y", "[]]"));
+        assert!(Pattern::regex("^BOB", true).unwrap().is_match("bob"));
+        assert!(matches!(Pattern::regex("(ab", false), Err(QueryError::InvalidPattern { .. })));
+        assert!(matches!(Pattern::regex("*a", false), Err(QueryError::InvalidPattern { .. })));
+        assert!(matches!(Pattern::regex("a{3,1}", false), Err(QueryError::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_patterns_that_would_backtrack() {
+        let text = "a".repeat(40);
+        assert!(!Pattern::regex("(a*)*b", false).unwrap().is_match(&text));
+        assert!(!like(&text, "%a%a%a%a%a%a%a%a%a%a%a%b"));
+        assert!(like(&format!("{}b", text), "%a%a%a%a%a%a%a%a%a%a%a%b"));
+
+        // Long texts do not use up the stack
+        let long = "ab".repeat(50_000);
+        assert!(like(&long, "%b"));
+        assert!(Pattern::regex("^(ab)+$", false).unwrap().is_match(&long));
+        assert!(Pattern::similar_to("(a|b)*", None).unwrap().is_match(&long));
+
+        assert!(matches!(Pattern::regex("((a{100}){100}){100}", false), Err(QueryError::InvalidPattern { .. })));
+        assert!(Pattern::regex("^(a|b){2,3}c?$", false).unwrap().is_match("aba"));
+        assert!(!Pattern::regex("^(a|b){2,3}$", false).unwrap().is_match("abab"));
+    }
+}
@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+
+use sqlparser::ast::{Expr, Ident};
+
+use crate::error::QueryError;
+use crate::result_set::ResultSet;
+use crate::schema::Table;
+use crate::value::{DataType, Value};
+
+// A column of an intermediate result: the table or alias it came from, its name and declared type.
+// Hidden fields are the per-table copies of columns a USING/NATURAL join merged; they can only be
+// reached with a qualified name (`s.id`) and are left out of `*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub qualifier: Option<String>,
+    pub name: String,
+    pub data_type: Option<DataType>,
+    pub hidden: bool,
+}
+
+impl Field {
+    pub fn new(qualifier: Option<String>, name: impl Into<String>, data_type: Option<DataType>) -> Self {
+        Field { qualifier, name: name.into(), data_type, hidden: false }
+    }
+
+    // A value computed per group or window, such as `COUNT(*)`, named after its SQL text. It is hidden and has
+    // no qualifier, so no column reference resolves to it; only resolve_computed finds it.
+    pub fn computed(expr: &Expr) -> Self {
+        Field { qualifier: None, name: expr.to_string(), data_type: None, hidden: true }
+    }
+}
+
+// The rows flowing through a query, with one field per value in each row
+#[derive(Debug, Clone, Default)]
+pub struct Relation {
+    pub fields: Vec<Field>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
+
+// Whether an identifier names a column called `name`: unquoted identifiers match it ignoring case, like SQL
+// folds them, while quoted ones ("Name") have to match exactly
+pub fn names_column(ident: &Ident, name: &str) -> bool {
+    match ident.quote_style {
+        Some(_) => ident.value == name,
+        None => ident.value.eq_ignore_ascii_case(name),
+    }
+}
+
+// Find the field a column reference (`name` or `qualifier.name`) points at. Unqualified names only see
+// visible fields and must be unique; a namespace in front of the qualifier (`school.student.id`) is ignored.
+// Returns Ok(None) when nothing matches.
+pub fn resolve_column(fields: &[Field], idents: &[Ident]) -> Result<Option<usize>, QueryError> {
+    let (qualifier, name) = match idents {
+        [name] => (None, name),
+        [.., qualifier, name] => (Some(qualifier), name),
+        [] => return Ok(None),
+    };
+    let mut matches = fields.iter().enumerate().filter(|(_, field)| {
+        names_column(name, &field.name)
+            && match qualifier {
+                None => !field.hidden,
+                Some(q) => field.qualifier.as_ref().is_some_and(|fq| fq.eq_ignore_ascii_case(&q.value)),
+            }
+    });
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Ok(Some(index)),
+        (Some(_), Some(_)) => Err(QueryError::AmbiguousColumn(
+            idents.iter().map(|i| i.value.as_str()).collect::<Vec<_>>().join("."),
+        )),
+        _ => Ok(None),
+    }
+}
+
+// Find the field holding the value of a computed expression
+pub fn resolve_computed(fields: &[Field], expr: &Expr) -> Option<usize> {
+    let name = expr.to_string();
+    fields.iter().position(|field| field.hidden && field.qualifier.is_none() && field.name == name)
+}
+
+impl Relation {
+    // All rows of a table, with its columns qualified by the table name or its alias. An alias may
+    // also rename the columns, e.g. `student AS s (sid, sname)`.
+    pub fn scan(table: &Table, qualifier: &str, column_aliases: &[Ident]) -> Result<Relation, QueryError> {
+        let names = table.columns();
+        if column_aliases.len() > names.len() {
+            return Err(QueryError::ColumnAliasCount {
+                relation: qualifier.to_string(),
+                expected: names.len(),
+                found: column_aliases.len(),
+            });
+        }
+        let fields = table
+            .schema
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let name = column_aliases.get(i).map_or(column.name.clone(), |alias| alias.value.clone());
+                Field::new(Some(qualifier.to_string()), name, Some(column.data_type))
+            })
+            .collect();
+        let rows = table
+            .rows()
+            .iter()
+            .map(|row| names.iter().map(|name| row.get(name).cloned().unwrap_or(Value::Null)).collect())
+            .collect();
+        Ok(Relation { fields, rows })
+    }
+
+    // The rows of a subquery in FROM, e.g. `(SELECT ...) AS s`, named after its output columns unless the alias
+    // renames them. A column's type is the type of its values, when they all have the same one.
+    pub fn derived(result: ResultSet, qualifier: Option<&str>, column_aliases: &[Ident]) -> Result<Relation, QueryError> {
+        if column_aliases.len() > result.columns.len() {
+            return Err(QueryError::ColumnAliasCount {
+                relation: qualifier.unwrap_or("subquery").to_string(),
+                expected: result.columns.len(),
+                found: column_aliases.len(),
+            });
+        }
+        let fields = result
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let name = column_aliases.get(i).map_or(column.clone(), |alias| alias.value.clone());
+                let mut types = result.rows.iter().filter_map(|row| row[i].data_type());
+                let data_type = types.next().filter(|first| types.all(|other| other == *first));
+                Field::new(qualifier.map(str::to_string), name, data_type)
+            })
+            .collect();
+        Ok(Relation { fields, rows: result.rows })
+    }
+
+    // Put every field under a new qualifier, e.g. for `(a JOIN b) AS j`
+    pub fn requalify(mut self, qualifier: &str) -> Relation {
+        for field in &mut self.fields {
+            field.qualifier = Some(qualifier.to_string());
+        }
+        self
+    }
+
+    // Nested-loop join: pair every left row with every right row the predicate accepts, then pad the
+    // unmatched rows of the outer side(s) with NULLs. The predicate sees the combined fields and row.
+    pub fn join<F>(self, right: Relation, kind: JoinKind, mut on: F) -> Result<Relation, QueryError>
+    where
+        F: FnMut(&[Field], &[Value]) -> Result<bool, QueryError>,
+    {
+        let fields: Vec<Field> = self.fields.iter().chain(&right.fields).cloned().collect();
+        let left_nulls = vec![Value::Null; self.fields.len()];
+        let right_nulls = vec![Value::Null; right.fields.len()];
+
+        let mut rows = Vec::new();
+        let mut right_matched = vec![false; right.rows.len()];
+        for left_row in &self.rows {
+            let mut matched = false;
+            for (i, right_row) in right.rows.iter().enumerate() {
+                let row: Vec<Value> = left_row.iter().chain(right_row).cloned().collect();
+                if kind == JoinKind::Cross || on(&fields, &row)? {
+                    matched = true;
+                    right_matched[i] = true;
+                    rows.push(row);
+                }
+            }
+            if !matched && matches!(kind, JoinKind::Left | JoinKind::Full) {
+                rows.push(left_row.iter().chain(&right_nulls).cloned().collect());
+            }
+        }
+        if matches!(kind, JoinKind::Right | JoinKind::Full) {
+            for (right_row, _) in right.rows.iter().zip(&right_matched).filter(|(_, matched)| !**matched) {
+                rows.push(left_nulls.iter().chain(right_row).cloned().collect());
+            }
+        }
+        Ok(Relation { fields, rows })
+    }
+
+    // Join on equality of the named columns. Each named column appears once, first, holding the
+    // left value or, for rows only the right side has, the right value; the per-table copies are hidden.
+    pub fn join_using(self, right: Relation, kind: JoinKind, columns: &[Ident]) -> Result<Relation, QueryError> {
+        let mut pairs = Vec::new();
+        for column in columns {
+            let side = |relation: &Relation, name: &str| {
+                resolve_column(&relation.fields, std::slice::from_ref(column))?.ok_or_else(|| QueryError::UnknownColumn {
+                    column: column.value.clone(),
+                    table: name.to_string(),
+                    clause: "FROM".to_string(),
+                    suggestion: None,
+                })
+            };
+            let left_index = side(&self, "left side of the join")?;
+            let right_index = side(&right, "right side of the join")?;
+            pairs.push((left_index, self.fields.len() + right_index));
+        }
+
+        let joined = self.join(right, kind, |_, row| {
+            for &(l, r) in &pairs {
+                if row[l].compare(&row[r])? != Some(Ordering::Equal) {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })?;
+
+        let mut fields: Vec<Field> = pairs
+            .iter()
+            .map(|&(l, r)| {
+                let data_type = joined.fields[l].data_type.or(joined.fields[r].data_type);
+                Field::new(None, joined.fields[l].name.clone(), data_type)
+            })
+            .collect();
+        fields.extend(joined.fields.iter().enumerate().map(|(i, field)| {
+            let merged = pairs.iter().any(|&(l, r)| i == l || i == r);
+            Field { hidden: field.hidden || merged, ..field.clone() }
+        }));
+        let rows = joined
+            .rows
+            .into_iter()
+            .map(|row| {
+                let mut merged: Vec<Value> = pairs
+                    .iter()
+                    .map(|&(l, r)| if row[l] == Value::Null { row[r].clone() } else { row[l].clone() })
+                    .collect();
+                merged.extend(row);
+                merged
+            })
+            .collect();
+        Ok(Relation { fields, rows })
+    }
+
+    // The names of the visible columns both relations have, for NATURAL joins
+    pub fn common_columns(&self, other: &Relation) -> Vec<Ident> {
+        let mut common: Vec<Ident> = Vec::new();
+        for field in self.fields.iter().filter(|f| !f.hidden) {
+            let shared = other.fields.iter().any(|f| !f.hidden && f.name == field.name);
+            if shared && !common.iter().any(|c| c.value == field.name) {
+                common.push(Ident::new(field.name.clone()));
+            }
+        }
+        common
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(qualifier: &str, names: &[&str], rows: Vec<Vec<Value>>) -> Relation {
+        let fields =
+            names.iter().map(|name| Field::new(Some(qualifier.to_string()), *name, Some(DataType::Integer))).collect();
+        Relation { fields, rows }
+    }
+
+    fn ids(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|v| Value::Integer(*v)).collect()
+    }
+
+    #[test]
+    fn test_resolve_qualified_and_ambiguous_columns() {
+        let joined = relation("a", &["id", "x"], vec![])
+            .join(relation("b", &["id"], vec![]), JoinKind::Cross, |_, _| Ok(true))
+            .unwrap();
+        assert_eq!(resolve_column(&joined.fields, &[Ident::new("x")]), Ok(Some(1)));
+        assert_eq!(resolve_column(&joined.fields, &[Ident::new("B"), Ident::new("id")]), Ok(Some(2)));
+        assert!(matches!(resolve_column(&joined.fields, &[Ident::new("id")]), Err(QueryError::AmbiguousColumn(_))));
+        assert_eq!(resolve_column(&joined.fields, &[Ident::new("y")]), Ok(None));
+        assert_eq!(resolve_column(&joined.fields, &[Ident::new("X")]), Ok(Some(1)));
+        assert_eq!(resolve_column(&joined.fields, &[Ident::with_quote('"', "X")]), Ok(None));
+        assert_eq!(resolve_column(&joined.fields, &[Ident::with_quote('"', "x")]), Ok(Some(1)));
+    }
+
+    #[test]
+    fn test_full_join_using_pads_and_merges() {
+        let left = relation("a", &["id", "x"], vec![ids(&[1, 10]), ids(&[2, 20])]);
+        let right = relation("b", &["id", "y"], vec![ids(&[2, 200]), ids(&[3, 300])]);
+        let joined = left.join_using(right, JoinKind::Full, &[Ident::new("id")]).unwrap();
+
+        let visible: Vec<&str> = joined.fields.iter().filter(|f| !f.hidden).map(|f| f.name.as_str()).collect();
+        assert_eq!(visible, vec!["id", "x", "y"]);
+        let merged_ids: Vec<Value> = joined.rows.iter().map(|row| row[0].clone()).collect();
+        assert_eq!(merged_ids, ids(&[1, 2, 3]));
+        assert_eq!(joined.rows[0][4], Value::Null);
+        assert_eq!(joined.rows[2][1], Value::Null);
+    }
+
+    #[test]
+    fn test_derived_relation_types_and_aliases() {
+        let mut result = ResultSet::new(vec!["id".to_string(), "score".to_string()]);
+        result.rows = vec![
+            vec![Value::Integer(1), Value::Integer(90)],
+            vec![Value::Integer(2), Value::Null],
+            vec![Value::Integer(3), Value::Float(72.5)],
+        ];
+        let derived = Relation::derived(result.clone(), Some("t"), &[Ident::new("sid")]).unwrap();
+        let names: Vec<&str> = derived.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["sid", "score"]);
+        assert_eq!(derived.fields[0].data_type, Some(DataType::Integer));
+        assert_eq!(derived.fields[1].data_type, None);
+        assert_eq!(resolve_column(&derived.fields, &[Ident::new("t"), Ident::new("sid")]), Ok(Some(0)));
+
+        let aliases = [Ident::new("a"), Ident::new("b"), Ident::new("c")];
+        assert!(matches!(
+            Relation::derived(result, Some("t"), &aliases),
+            Err(QueryError::ColumnAliasCount { expected: 2, found: 3, .. })
+        ));
+    }
+}
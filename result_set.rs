@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::value::Value;
+
+// The output of a query: column headers in projection order and one vector of values per row,
+// so results print the same way on every run
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    pub fn new(columns: Vec<String>) -> Self {
+        ResultSet { columns, rows: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    // Looking rows up by column name is only for the tests, which check results a cell at a time
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    #[cfg(test)]
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+
+    // The value of the named column in the given row
+    #[cfg(test)]
+    pub fn get(&self, row: usize, column: &str) -> Option<&Value> {
+        let index = self.column_index(column)?;
+        self.rows.get(row)?.get(index)
+    }
+}
+
+// Render as a plain-text table, e.g.
+//
+//   +----+-------+
+//   | id | name  |
+//   +----+-------+
+//   | 1  | Alice |
+//   +----+-------+
+impl fmt::Display for ResultSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<Vec<String>> =
+            self.rows.iter().map(|row| row.iter().map(Value::to_string).collect()).collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                cells.iter().map(|row| row[i].chars().count()).chain([column.chars().count()]).max().unwrap_or(0)
+            })
+            .collect();
+
+        let border: String = widths.iter().map(|w| format!("+{}", "-".repeat(w + 2))).collect::<String>() + "+";
+        let line = |values: &[String]| -> String {
+            let mut out = String::new();
+            for (value, width) in values.iter().zip(&widths) {
+                out.push_str(&format!("| {:<width$} ", value, width = width));
+            }
+            out + "|"
+        };
+
+        writeln!(f, "{}", border)?;
+        writeln!(f, "{}", line(&self.columns))?;
+        writeln!(f, "{}", border)?;
+        for row in &cells {
+            writeln!(f, "{}", line(row))?;
+        }
+        write!(f, "{}", border)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table() {
+        let mut result = ResultSet::new(vec!["id".to_string(), "name".to_string()]);
+        result.rows.push(vec![Value::Integer(1), Value::from("Alice")]);
+        result.rows.push(vec![Value::Integer(10), Value::Null]);
+        assert_eq!(
+            result.to_string(),
+            "+----+-------+\n\
+             | id | name  |\n\
+             +----+-------+\n\
+             | 1  | Alice |\n\
+             | 10 | NULL  |\n\
+             +----+-------+"
+        );
+    }
+
+    #[test]
+    fn test_lookup_by_column() {
+        let mut result = ResultSet::new(vec!["id".to_string(), "name".to_string()]);
+        assert!(result.is_empty());
+        result.rows.push(vec![Value::Integer(1), Value::from("Alice")]);
+        assert_eq!((result.len(), result.is_empty()), (1, false));
+        assert_eq!(result.column_index("name"), Some(1));
+        assert_eq!(result.get(0, "name"), Some(&Value::from("Alice")));
+        assert_eq!(result.get(0, "major"), None);
+        assert_eq!(result.get(1, "id"), None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::value::{DataType, Value};
+
+pub type Row = HashMap<String, Value>;
+
+// A declared column: its type, whether it accepts NULL and what to store when a row leaves it out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+    pub default: Option<Value>,
+}
+
+impl Column {
+    // A nullable column without a default
+    pub fn new(name: impl Into<String>, data_type: DataType) -> Self {
+        Column { name: name.into(), data_type, nullable: true, default: None }
+    }
+
+    pub fn not_null(mut self) -> Self {
+        self.nullable = false;
+        self
+    }
+
+    pub fn with_default(mut self, default: Value) -> Self {
+        self.default = Some(default);
+        self
+    }
+}
+
+// The ordered list of columns a table is declared with
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+}
+
+impl Schema {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Schema { columns }
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|column| column.name.clone()).collect()
+    }
+}
+
+// Why a row could not be inserted into a table
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    // The row has a value for a column the schema does not declare
+    UnknownColumn { table: String, column: String },
+    // A NOT NULL column without a default was left out or set to NULL
+    MissingValue { table: String, column: String },
+    // The value cannot be stored in the column's type
+    TypeMismatch { table: String, column: String, expected: DataType, found: Value },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::UnknownColumn { table, column } => {
+                write!(f, "table '{}' has no column '{}'", table, column)
+            }
+            SchemaError::MissingValue { table, column } => {
+                write!(f, "column '{}' of table '{}' cannot be NULL", column, table)
+            }
+            SchemaError::TypeMismatch { table, column, expected, found } => write!(
+                f,
+                "column '{}' of table '{}' has type {}, but got {}",
+                column,
+                table,
+                expected,
+                found.describe()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+pub struct Table {
+    pub name: String,
+    pub schema: Schema,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn new(name: impl Into<String>, schema: Schema) -> Self {
+        Table { name: name.into(), schema, rows: Vec::new() }
+    }
+
+    // Build a table and insert all of the given rows, stopping at the first one that does not fit the schema
+    pub fn with_rows(name: impl Into<String>, schema: Schema, rows: Vec<Row>) -> Result<Self, SchemaError> {
+        let mut table = Table::new(name, schema);
+        for row in rows {
+            table.insert(row)?;
+        }
+        Ok(table)
+    }
+
+    // Validate a row against the schema and store it; values are coerced to the column types,
+    // and columns the row leaves out get their default (or NULL)
+    pub fn insert(&mut self, mut row: Row) -> Result<(), SchemaError> {
+        if let Some(column) = row.keys().find(|name| self.schema.column(name).is_none()) {
+            return Err(SchemaError::UnknownColumn { table: self.name.clone(), column: column.clone() });
+        }
+
+        let mut stored = Row::new();
+        for column in &self.schema.columns {
+            let value = match row.remove(&column.name) {
+                Some(value) => value,
+                None => column.default.clone().unwrap_or(Value::Null),
+            };
+            let value = column.data_type.coerce(&value).ok_or_else(|| SchemaError::TypeMismatch {
+                table: self.name.clone(),
+                column: column.name.clone(),
+                expected: column.data_type,
+                found: value.clone(),
+            })?;
+            if value == Value::Null && !column.nullable {
+                return Err(SchemaError::MissingValue { table: self.name.clone(), column: column.name.clone() });
+            }
+            stored.insert(column.name.clone(), value);
+        }
+        self.rows.push(stored);
+        Ok(())
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    // The declared column names, in schema order
+    pub fn columns(&self) -> Vec<String> {
+        self.schema.column_names()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Column::new("id", DataType::Integer).not_null(),
+            Column::new("gpa", DataType::Float),
+            Column::new("active", DataType::Boolean).with_default(Value::Bool(true)),
+        ])
+    }
+
+    #[test]
+    fn test_insert_fills_defaults_and_coerces() {
+        let mut table = Table::new("student", schema());
+        table.insert(hashmap! {"id".to_string() => Value::Integer(1), "gpa".to_string() => Value::Integer(3)}).unwrap();
+        let row = &table.rows()[0];
+        assert_eq!(row["gpa"], Value::Float(3.0));
+        assert_eq!(row["active"], Value::Bool(true));
+    }
+
+    #[test]
+    fn test_insert_rejects_invalid_rows() {
+        let mut table = Table::new("student", schema());
+        assert_eq!(
+            table.insert(hashmap! {"gpa".to_string() => Value::Float(3.5)}),
+            Err(SchemaError::MissingValue { table: "student".to_string(), column: "id".to_string() })
+        );
+        assert!(matches!(
+            table.insert(hashmap! {"id".to_string() => Value::from("one")}),
+            Err(SchemaError::TypeMismatch { expected: DataType::Integer, .. })
+        ));
+        assert!(matches!(
+            table.insert(hashmap! {"id".to_string() => Value::Integer(1), "age".to_string() => Value::Integer(20)}),
+            Err(SchemaError::UnknownColumn { .. })
+        ));
+        assert!(table.rows().is_empty());
+    }
+}
@@ -0,0 +1,743 @@
+use sqlparser::ast::{
+    self, BinaryOperator, Expr, FunctionArg, FunctionArgExpr, Ident, JoinConstraint, JoinOperator, Select, SelectItem,
+    WindowType,
+};
+
+use crate::aggregate::{is_aggregate, Aggregate, AggregateFunction};
+use crate::diagnostic::Severity;
+use crate::error::QueryError;
+use crate::eval::OuterRow;
+use crate::functions::{invalid_argument, scalar_call, ArgType, FunctionRegistry, Returns, Signature};
+use crate::relation::{names_column, resolve_column, Field};
+use crate::value::{DataType, Value};
+use crate::window::{is_window, WindowCall, WindowFunction};
+
+// Knobs for the semantic checks that run after a query has been parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationOptions {
+    // Whether a column that does not exist rejects the query or is only reported as a warning
+    pub unknown_columns: Severity,
+    // Whether HAVING in a query that is not grouped rejects it, as standard SQL does, or is only reported as a
+    // warning and filters the rows like a second WHERE, as in dialects such as MySQL
+    pub having_without_grouping: Severity,
+    // Values for the query's parameters: `$1` is the first one, and each `?` takes the next
+    pub parameters: Vec<Value>,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        ValidationOptions {
+            unknown_columns: Severity::Error,
+            having_without_grouping: Severity::Error,
+            parameters: Vec::new(),
+        }
+    }
+}
+
+// Collect every column reference (`name` or `table.name`) used inside an expression
+// (subqueries are not descended into)
+pub fn collect_identifiers<'a>(expr: &'a Expr, out: &mut Vec<&'a [Ident]>) {
+    walk(expr, &mut |node| {
+        push_identifier(node, out);
+        true
+    })
+}
+
+// Like collect_identifiers, but stopping at aggregate calls, which are collected into `aggregates`
+pub fn collect_ungrouped<'a>(
+    expr: &'a Expr,
+    functions: &FunctionRegistry,
+    out: &mut Vec<&'a [Ident]>,
+    aggregates: &mut Vec<&'a Expr>,
+) {
+    walk(expr, &mut |node| {
+        if is_aggregate(node, functions) {
+            aggregates.push(node);
+            return false;
+        }
+        push_identifier(node, out);
+        true
+    })
+}
+
+fn push_identifier<'a>(expr: &'a Expr, out: &mut Vec<&'a [Ident]>) {
+    match expr {
+        Expr::Identifier(id) => out.push(std::slice::from_ref(id)),
+        Expr::CompoundIdentifier(ids) => out.push(ids),
+        _ => {}
+    }
+}
+
+// Visit an expression and everything inside it, parents before children; `visit` returns whether to look
+// inside the expression it was given. Subqueries are not descended into.
+fn walk<'a>(expr: &'a Expr, visit: &mut dyn FnMut(&'a Expr) -> bool) {
+    if !visit(expr) {
+        return;
+    }
+    match expr {
+        Expr::BinaryOp { left, right, .. }
+        | Expr::IsDistinctFrom(left, right)
+        | Expr::IsNotDistinctFrom(left, right) => {
+            walk(left, visit);
+            walk(right, visit);
+        }
+        Expr::UnaryOp { expr, .. }
+        | Expr::Nested(expr)
+        | Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::IsTrue(expr)
+        | Expr::IsNotTrue(expr)
+        | Expr::IsFalse(expr)
+        | Expr::IsNotFalse(expr)
+        | Expr::IsUnknown(expr)
+        | Expr::IsNotUnknown(expr)
+        | Expr::Cast { expr, .. }
+        | Expr::TryCast { expr, .. }
+        | Expr::SafeCast { expr, .. }
+        | Expr::Ceil { expr, .. }
+        | Expr::Floor { expr, .. }
+        | Expr::InSubquery { expr, .. } => walk(expr, visit),
+        Expr::InList { expr, list, .. } => {
+            walk(expr, visit);
+            list.iter().for_each(|item| walk(item, visit));
+        }
+        Expr::Between { expr, low, high, .. } => {
+            walk(expr, visit);
+            walk(low, visit);
+            walk(high, visit);
+        }
+        Expr::Like { expr, pattern, .. }
+        | Expr::ILike { expr, pattern, .. }
+        | Expr::SimilarTo { expr, pattern, .. } => {
+            walk(expr, visit);
+            walk(pattern, visit);
+        }
+        Expr::Substring { expr, substring_from, substring_for } => {
+            walk(expr, visit);
+            substring_from.iter().chain(substring_for).for_each(|expr| walk(expr, visit));
+        }
+        Expr::Trim { expr, trim_what, .. } => {
+            walk(expr, visit);
+            trim_what.iter().for_each(|expr| walk(expr, visit));
+        }
+        Expr::Function(function) => {
+            for arg in &function.args {
+                if let FunctionArg::Named { arg: FunctionArgExpr::Expr(expr), .. }
+                | FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) = arg
+                {
+                    walk(expr, visit);
+                }
+            }
+            if let Some(WindowType::WindowSpec(spec)) = &function.over {
+                let ordering = spec.order_by.iter().map(|order| &order.expr);
+                spec.partition_by.iter().chain(ordering).for_each(|expr| walk(expr, visit));
+            }
+        }
+        Expr::Case { operand, conditions, results, else_result } => {
+            operand.iter().for_each(|expr| walk(expr, visit));
+            conditions.iter().chain(results).for_each(|expr| walk(expr, visit));
+            else_result.iter().for_each(|expr| walk(expr, visit));
+        }
+        _ => {}
+    }
+}
+
+// The expressions of the projection, WHERE, GROUP BY, HAVING and join conditions, in that order, each with
+// the clause it belongs to (join conditions are part of FROM)
+pub fn clause_exprs(select: &Select) -> Vec<(&'static str, &Expr)> {
+    let mut exprs: Vec<(&str, &Expr)> = projected(select).map(|expr| ("SELECT", expr)).collect();
+    exprs.extend(select.selection.iter().map(|expr| ("WHERE", expr)));
+    exprs.extend(select.group_by.iter().map(|expr| ("GROUP BY", expr)));
+    exprs.extend(select.having.iter().map(|expr| ("HAVING", expr)));
+    for join in select.from.iter().flat_map(|from| &from.joins) {
+        if let JoinOperator::Inner(JoinConstraint::On(on))
+        | JoinOperator::LeftOuter(JoinConstraint::On(on))
+        | JoinOperator::RightOuter(JoinConstraint::On(on))
+        | JoinOperator::FullOuter(JoinConstraint::On(on)) = &join.join_operator
+        {
+            exprs.push(("FROM", on));
+        }
+    }
+    exprs
+}
+
+// Resolve every column referenced by the projection, WHERE, GROUP BY, HAVING and join conditions against the
+// fields of the FROM clause, or else the enclosing rows of a correlated subquery. Unknown columns are returned
+// in the order they appear, so the caller can decide whether they are errors or warnings; ambiguous references
+// are always an error.
+pub fn check_columns(
+    select: &Select,
+    fields: &[Field],
+    outer: Option<&OuterRow>,
+    tables: &str,
+) -> Result<Vec<QueryError>, QueryError> {
+    let mut identifiers = Vec::new();
+    for (clause, expr) in clause_exprs(select) {
+        let mut found = Vec::new();
+        collect_identifiers(expr, &mut found);
+        identifiers.extend(found.into_iter().map(|idents| (clause, idents)));
+    }
+
+    // A column is reported once, where it is first used
+    let mut errors: Vec<QueryError> = Vec::new();
+    let mut reported: Vec<&[Ident]> = Vec::new();
+    for (clause, idents) in identifiers {
+        if resolve_column(fields, idents)?.is_some() || reported.contains(&idents) {
+            continue;
+        }
+        if let Some(outer) = outer
+            && outer.column(idents)?.is_some()
+        {
+            continue;
+        }
+        reported.push(idents);
+        errors.push(unknown_column(idents, fields, tables, clause));
+    }
+    Ok(errors)
+}
+
+// Check every function call before any rows are read: scalar functions have to exist and get the right
+// number of arguments, and arguments whose type is already known (columns, literals, casts and the
+// results of other calls) have to fit their parameters. SUM and AVG need numbers, and CAST a type it
+// can convert to.
+pub fn check_functions(expr: &Expr, fields: &[Field], functions: &FunctionRegistry) -> Result<(), QueryError> {
+    let mut result = Ok(());
+    walk(expr, &mut |node| {
+        if result.is_ok() {
+            result = check_call(node, fields, functions);
+        }
+        result.is_ok()
+    });
+    result
+}
+
+fn check_call(expr: &Expr, fields: &[Field], functions: &FunctionRegistry) -> Result<(), QueryError> {
+    if let Expr::Function(function) = expr
+        && let Some(aggregate) = Aggregate::parse(function, functions)?
+    {
+        return check_aggregate(&aggregate, fields, functions);
+    }
+    if let Expr::Function(function) = expr
+        && let Some(window) = WindowCall::parse(function, functions)?
+    {
+        return check_window(&window, fields, functions);
+    }
+    if let Some(call) = scalar_call(expr)? {
+        let function = functions.get(&call.name).ok_or_else(|| QueryError::UnknownFunction(call.name.clone()))?;
+        function.signature.check_arity(&function.name, call.args.len())?;
+        for (index, arg) in call.args.into_iter().enumerate() {
+            let param = function.signature.param(index).unwrap_or(ArgType::Any);
+            if let Some(found) = mismatched_argument(arg, param, fields, functions) {
+                return Err(invalid_argument(&function.name, index, param, &found));
+            }
+        }
+        return Ok(());
+    }
+    match expr {
+        Expr::Cast { expr: inner, data_type } => {
+            let unsupported = || QueryError::UnsupportedExpression(expr.to_string());
+            let target = DataType::from_sql(data_type).ok_or_else(unsupported)?;
+            match operand(inner, fields) {
+                Operand::Literal(value) => value.cast(target).map(|_| ()),
+                _ => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+// SUM and AVG need numbers, and user-defined aggregates arguments that fit their signature
+fn check_aggregate(aggregate: &Aggregate, fields: &[Field], functions: &FunctionRegistry) -> Result<(), QueryError> {
+    match &aggregate.function {
+        AggregateFunction::Sum | AggregateFunction::Avg => {
+            if let Some(found) = mismatched_argument(aggregate.args[0], ArgType::Numeric, fields, functions) {
+                return Err(QueryError::InvalidArguments {
+                    function: aggregate.function.name().to_string(),
+                    message: format!("expected a number, found {}", found),
+                });
+            }
+        }
+        AggregateFunction::User(user) => {
+            for (index, arg) in aggregate.args.iter().enumerate() {
+                let param = user.signature.param(index).unwrap_or(ArgType::Any);
+                if let Some(found) = mismatched_argument(arg, param, fields, functions) {
+                    return Err(invalid_argument(&user.name, index, param, &found));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Window calls cannot be nested, and their arguments have to fit like those of any other function
+fn check_window(window: &WindowCall, fields: &[Field], functions: &FunctionRegistry) -> Result<(), QueryError> {
+    let inner = window.args.iter().copied().chain(window.partition_by).chain(window.order_by.iter().map(|order| &order.expr));
+    for expr in inner {
+        if let Some(nested) = windows_in(expr).first() {
+            return Err(QueryError::MisplacedWindow {
+                function: nested.to_string(),
+                clause: "another window function".to_string(),
+            });
+        }
+    }
+    match (&window.function, window.function.signature()) {
+        (WindowFunction::Aggregate(aggregate), _) => check_aggregate(aggregate, fields, functions),
+        (_, Some(signature)) => {
+            for (index, arg) in window.args.iter().enumerate() {
+                let param = signature.param(index).unwrap_or(ArgType::Any);
+                if let Some(found) = mismatched_argument(arg, param, fields, functions) {
+                    return Err(invalid_argument(&window.name, index, param, &found));
+                }
+            }
+            Ok(())
+        }
+        (_, None) => Ok(()),
+    }
+}
+
+// Describe an argument that cannot be passed for `param`, or None if it fits (or its type is not known).
+// Text literals fit wherever their text reads as the parameter's type, e.g. ABS('-2').
+fn mismatched_argument(arg: &Expr, param: ArgType, fields: &[Field], functions: &FunctionRegistry) -> Option<String> {
+    match operand(arg, fields) {
+        Operand::Literal(value) => param.convert(&value).is_none().then(|| value.describe()),
+        Operand::Column(column, data_type) => (!param.accepts(data_type)).then(|| describe_column(column, data_type)),
+        Operand::Other => infer_type(arg, fields, functions)
+            .filter(|data_type| !param.accepts(*data_type))
+            .map(|data_type| data_type.to_string()),
+    }
+}
+
+// The type an expression will have, where that is known without reading any rows
+fn infer_type(expr: &Expr, fields: &[Field], functions: &FunctionRegistry) -> Option<DataType> {
+    if let Expr::Cast { data_type, .. } = expr {
+        return DataType::from_sql(data_type);
+    }
+    if let Expr::Nested(inner) = expr {
+        return infer_type(inner, fields, functions);
+    }
+    if let Expr::Function(function) = expr
+        && let Ok(Some(aggregate)) = Aggregate::parse(function, functions)
+    {
+        return aggregate_type(&aggregate, fields, functions);
+    }
+    if let Expr::Function(function) = expr
+        && let Ok(Some(window)) = WindowCall::parse(function, functions)
+    {
+        return match (&window.function, window.function.signature()) {
+            (WindowFunction::Aggregate(aggregate), _) => aggregate_type(aggregate, fields, functions),
+            (_, Some(Signature { returns: Returns::Type(data_type), .. })) => Some(data_type),
+            _ => infer_type(window.args.first()?, fields, functions),
+        };
+    }
+    if let Ok(Some(call)) = scalar_call(expr) {
+        return match functions.get(&call.name)?.signature.returns {
+            Returns::Type(data_type) => Some(data_type),
+            Returns::FirstArgument => infer_type(call.args.first()?, fields, functions),
+        };
+    }
+    match operand(expr, fields) {
+        Operand::Column(_, data_type) => Some(data_type),
+        Operand::Literal(value) => value.data_type(),
+        Operand::Other => None,
+    }
+}
+
+fn aggregate_type(aggregate: &Aggregate, fields: &[Field], functions: &FunctionRegistry) -> Option<DataType> {
+    match &aggregate.function {
+        AggregateFunction::Count => Some(DataType::Integer),
+        AggregateFunction::Avg => Some(DataType::Float),
+        AggregateFunction::User(user) => match user.signature.returns {
+            Returns::Type(data_type) => Some(data_type),
+            Returns::FirstArgument => infer_type(aggregate.args.first()?, fields, functions),
+        },
+        _ => infer_type(aggregate.args.first()?, fields, functions),
+    }
+}
+
+// Report a column reference in the given clause that matched nothing, suggesting a column of the same table
+// if it was qualified
+pub fn unknown_column(idents: &[Ident], fields: &[Field], tables: &str, clause: &str) -> QueryError {
+    let name = &idents[idents.len() - 1].value;
+    let qualifier = idents.len().checked_sub(2).map(|i| &idents[i].value);
+    let candidates: Vec<String> = fields
+        .iter()
+        .filter(|field| match qualifier {
+            None => !field.hidden,
+            Some(q) => field.qualifier.as_ref().is_some_and(|fq| fq.eq_ignore_ascii_case(q)),
+        })
+        .map(|field| field.name.clone())
+        .collect();
+    QueryError::UnknownColumn {
+        column: idents.iter().map(|i| i.value.as_str()).collect::<Vec<_>>().join("."),
+        table: qualifier.cloned().unwrap_or_else(|| tables.to_string()),
+        clause: clause.to_string(),
+        suggestion: suggest(name, &candidates),
+    }
+}
+
+// The projected expressions, skipping wildcards
+fn projected(select: &Select) -> impl Iterator<Item = &Expr> {
+    select.projection.iter().filter_map(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+        _ => None,
+    })
+}
+
+fn aggregates_in<'a>(expr: &'a Expr, functions: &FunctionRegistry) -> Vec<&'a Expr> {
+    let mut aggregates = Vec::new();
+    collect_ungrouped(expr, functions, &mut Vec::new(), &mut aggregates);
+    aggregates
+}
+
+// The window calls in an expression, not counting any nested inside them
+pub fn windows_in(expr: &Expr) -> Vec<&Expr> {
+    let mut windows = Vec::new();
+    walk(expr, &mut |node| {
+        if is_window(node) {
+            windows.push(node);
+            return false;
+        }
+        true
+    });
+    windows
+}
+
+// Window functions run over the rows that are left after grouping, so they can only be used in the
+// select list and ORDER BY
+pub fn check_window_placement(select: &Select) -> Result<(), QueryError> {
+    let mut clauses: Vec<(&str, &Expr)> = Vec::new();
+    clauses.extend(select.selection.iter().map(|expr| ("WHERE", expr)));
+    clauses.extend(select.group_by.iter().map(|expr| ("GROUP BY", expr)));
+    clauses.extend(select.having.iter().map(|expr| ("HAVING", expr)));
+    for join in select.from.iter().flat_map(|from| &from.joins) {
+        if let JoinOperator::Inner(JoinConstraint::On(on))
+        | JoinOperator::LeftOuter(JoinConstraint::On(on))
+        | JoinOperator::RightOuter(JoinConstraint::On(on))
+        | JoinOperator::FullOuter(JoinConstraint::On(on)) = &join.join_operator
+        {
+            clauses.push(("JOIN conditions", on));
+        }
+    }
+    for (clause, expr) in clauses {
+        if let Some(window) = windows_in(expr).first() {
+            return Err(QueryError::MisplacedWindow { function: window.to_string(), clause: clause.to_string() });
+        }
+    }
+    Ok(())
+}
+
+// Whether the query computes one row per group: it has a GROUP BY clause, or aggregates in its
+// projection or HAVING clause
+pub fn is_grouped(select: &Select, functions: &FunctionRegistry) -> bool {
+    !select.group_by.is_empty()
+        || projected(select).chain(&select.having).any(|expr| !aggregates_in(expr, functions).is_empty())
+}
+
+// Aggregates only make sense once rows are grouped, so they cannot appear in WHERE, GROUP BY or a join
+// condition
+pub fn check_aggregate_placement(select: &Select, functions: &FunctionRegistry) -> Result<(), QueryError> {
+    let misplaced = |clause: &str, expr: &Expr| match aggregates_in(expr, functions).first() {
+        Some(aggregate) => {
+            Err(QueryError::MisplacedAggregate { aggregate: aggregate.to_string(), clause: clause.to_string() })
+        }
+        None => Ok(()),
+    };
+    if let Some(selection) = &select.selection {
+        misplaced("WHERE", selection)?;
+    }
+    for expr in &select.group_by {
+        misplaced("GROUP BY", expr)?;
+    }
+    for join in select.from.iter().flat_map(|from| &from.joins) {
+        if let JoinOperator::Inner(JoinConstraint::On(on))
+        | JoinOperator::LeftOuter(JoinConstraint::On(on))
+        | JoinOperator::RightOuter(JoinConstraint::On(on))
+        | JoinOperator::FullOuter(JoinConstraint::On(on)) = &join.join_operator
+        {
+            misplaced("JOIN conditions", on)?;
+        }
+    }
+    Ok(())
+}
+
+// GROUP BY items can name an output column by its 1-based position or its alias, like ORDER BY keys.
+// Returns the query with those items replaced by the expressions they stand for, or None if it has none.
+// A name that is a column of FROM stays that column, even when an output column has it as its alias.
+pub fn resolve_group_by(select: &Select, fields: &[Field]) -> Result<Option<Select>, QueryError> {
+    let mut group_by = Vec::new();
+    let mut changed = false;
+    for expr in &select.group_by {
+        let resolved = match expr {
+            Expr::Value(ast::Value::Number(n, _)) => {
+                let count = select.projection.len();
+                match n.parse::<usize>().ok().filter(|position| (1..=count).contains(position)) {
+                    Some(position) => match &select.projection[position - 1] {
+                        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr.clone()),
+                        _ => return Err(QueryError::UnsupportedExpression(format!("GROUP BY {}", n))),
+                    },
+                    None => return Err(QueryError::InvalidOrdinal { position: n.clone(), count }),
+                }
+            }
+            Expr::Identifier(id) if resolve_column(fields, std::slice::from_ref(id))?.is_none() => {
+                select.projection.iter().find_map(|item| match item {
+                    SelectItem::ExprWithAlias { expr, alias } if names_column(id, &alias.value) => Some(expr.clone()),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+        changed |= resolved.is_some();
+        group_by.push(resolved.unwrap_or_else(|| expr.clone()));
+    }
+    Ok(changed.then(|| Select { group_by, ..select.clone() }))
+}
+
+// In a grouped query each output row stands for a whole group, so every column used outside of an
+// aggregate in the projection or HAVING has to be one of the GROUP BY columns (or the expression has to
+// be a GROUP BY expression itself). Columns that do not exist at all are left to check_columns.
+pub fn check_grouping(select: &Select, fields: &[Field], functions: &FunctionRegistry) -> Result<(), QueryError> {
+    if !is_grouped(select, functions) {
+        return Ok(());
+    }
+    if let Some(wildcard) = select.projection.iter().find(|item| {
+        matches!(item, SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..))
+    }) {
+        return Err(QueryError::NotGrouped { column: wildcard.to_string(), clause: "SELECT".to_string() });
+    }
+    for expr in projected(select) {
+        check_grouped_expr(select, fields, expr, functions, "SELECT")?;
+    }
+    if let Some(having) = &select.having {
+        check_grouped_expr(select, fields, having, functions, "HAVING")?;
+    }
+    Ok(())
+}
+
+// Check a single expression of the given clause evaluated once per group, e.g. an ORDER BY key of a
+// grouped query
+pub fn check_grouped_expr(
+    select: &Select,
+    fields: &[Field],
+    expr: &Expr,
+    functions: &FunctionRegistry,
+    clause: &str,
+) -> Result<(), QueryError> {
+    if select.group_by.contains(expr) {
+        return Ok(());
+    }
+    let mut grouped = Vec::new();
+    for group_expr in &select.group_by {
+        let mut identifiers = Vec::new();
+        collect_identifiers(group_expr, &mut identifiers);
+        for idents in identifiers {
+            grouped.extend(resolve_column(fields, idents)?);
+        }
+    }
+
+    let mut identifiers = Vec::new();
+    collect_ungrouped(expr, functions, &mut identifiers, &mut Vec::new());
+    for idents in identifiers {
+        if let Some(index) = resolve_column(fields, idents)?
+            && !grouped.contains(&index)
+        {
+            return Err(QueryError::NotGrouped {
+                column: idents.iter().map(|i| i.value.as_str()).collect::<Vec<_>>().join("."),
+                clause: clause.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// The number of rows a LIMIT, OFFSET or FETCH clause stands for. It has to be a non-negative integer
+// constant, or a parameter bound to one; NULL means no limit. `?` parameters are numbered in the order
+// the clauses are checked in (LIMIT, OFFSET, FETCH), counted with `unnamed`.
+pub fn row_count(
+    expr: &Expr,
+    clause: &str,
+    parameters: &[Value],
+    unnamed: &mut usize,
+) -> Result<Option<usize>, QueryError> {
+    let invalid = |value: String| QueryError::InvalidLimit { clause: clause.to_string(), value };
+    let value = match expr {
+        Expr::Value(ast::Value::Number(n, _)) => return n.parse().map(Some).map_err(|_| invalid(n.clone())),
+        Expr::Value(ast::Value::Null) => return Ok(None),
+        Expr::Value(ast::Value::Placeholder(name)) => {
+            let index = match name.strip_prefix('$') {
+                Some(number) => match number.parse::<usize>() {
+                    Ok(number) if number >= 1 => number - 1,
+                    _ => return Err(invalid(name.clone())),
+                },
+                None if name == "?" => {
+                    *unnamed += 1;
+                    *unnamed - 1
+                }
+                None => return Err(invalid(name.clone())),
+            };
+            parameters.get(index).ok_or_else(|| QueryError::UnboundParameter(name.clone()))?
+        }
+        other => return Err(invalid(other.to_string())),
+    };
+    match value {
+        Value::Integer(n) if *n >= 0 => Ok(Some(*n as usize)),
+        Value::Null => Ok(None),
+        other => Err(invalid(other.describe())),
+    }
+}
+
+// What the type checker knows about one side of a comparison without looking at any rows
+enum Operand<'a> {
+    Column(&'a Field, DataType),
+    Literal(Value),
+    Other,
+}
+
+fn operand<'a>(expr: &Expr, fields: &'a [Field]) -> Operand<'a> {
+    let idents = match expr {
+        Expr::Identifier(id) => std::slice::from_ref(id),
+        Expr::CompoundIdentifier(ids) => ids.as_slice(),
+        Expr::Value(literal) => return Value::from_literal(literal).map_or(Operand::Other, Operand::Literal),
+        Expr::Nested(expr) => return operand(expr, fields),
+        _ => return Operand::Other,
+    };
+    match resolve_column(fields, idents) {
+        Ok(Some(index)) => match fields[index].data_type {
+            Some(data_type) => Operand::Column(&fields[index], data_type),
+            None => Operand::Other,
+        },
+        _ => Operand::Other,
+    }
+}
+
+// Whether a literal can be compared with a column of the given type; text literals must parse as that type
+fn literal_fits(data_type: DataType, value: &Value) -> bool {
+    match value.data_type() {
+        None => true,
+        Some(DataType::Text) => {
+            // Text compared with a number may hold a float even when the column is an integer
+            let target = if data_type == DataType::Integer { DataType::Float } else { data_type };
+            target.coerce(value).is_some()
+        }
+        Some(literal_type) => literal_type.comparable_with(&data_type),
+    }
+}
+
+fn describe_column(field: &Field, data_type: DataType) -> String {
+    format!("{} column '{}'", data_type, field.name)
+}
+
+// Check one comparison of `expr`, which is named in the error so its diagnostic can point at it
+fn check_comparison(expr: &Expr, left: &Expr, right: &Expr, fields: &[Field]) -> Result<(), QueryError> {
+    let (left, right) = match (operand(left, fields), operand(right, fields)) {
+        (Operand::Column(column, data_type), Operand::Literal(value))
+        | (Operand::Literal(value), Operand::Column(column, data_type))
+            if !literal_fits(data_type, &value) =>
+        {
+            (describe_column(column, data_type), value.describe())
+        }
+        (Operand::Column(a, a_type), Operand::Column(b, b_type)) if !a_type.comparable_with(&b_type) => {
+            (describe_column(a, a_type), describe_column(b, b_type))
+        }
+        _ => return Ok(()),
+    };
+    Err(QueryError::TypeMismatch { left, right, expr: Some(expr.to_string()) })
+}
+
+// Check that both sides of every comparison have compatible types using the declared column types,
+// so that e.g. `id = 'one'` is rejected even when the table has no rows to evaluate it on
+pub fn check_types(expr: &Expr, fields: &[Field]) -> Result<(), QueryError> {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op:
+                BinaryOperator::Eq
+                | BinaryOperator::NotEq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq,
+            right,
+        } => check_comparison(expr, left, right, fields),
+        // IN and BETWEEN compare their operand with every value in the list or bound
+        Expr::InList { expr: operand, list, .. } => {
+            list.iter().try_for_each(|item| check_comparison(expr, operand, item, fields))
+        }
+        Expr::Between { expr: operand, low, high, .. } => {
+            check_comparison(expr, operand, low, fields)?;
+            check_comparison(expr, operand, high, fields)
+        }
+        // Pattern matching only works on text, so e.g. an INTEGER column LIKE '1%' is rejected up front
+        Expr::Like { expr: text, pattern, .. }
+        | Expr::ILike { expr: text, pattern, .. }
+        | Expr::SimilarTo { expr: text, pattern, .. } => match operand(text, fields) {
+            Operand::Column(column, data_type) if data_type != DataType::Text => {
+                let operator = match expr {
+                    Expr::Like { .. } => "LIKE",
+                    Expr::ILike { .. } => "ILIKE",
+                    _ => "SIMILAR TO",
+                };
+                Err(QueryError::InvalidOperands {
+                    operator: operator.to_string(),
+                    left: Some(describe_column(column, data_type)),
+                    right: pattern.to_string(),
+                })
+            }
+            _ => Ok(()),
+        },
+        Expr::BinaryOp { left, right, .. } => {
+            check_types(left, fields)?;
+            check_types(right, fields)
+        }
+        Expr::Nested(expr) | Expr::UnaryOp { expr, .. } => check_types(expr, fields),
+        _ => Ok(()),
+    }
+}
+
+// Pick the closest known name, if any is close enough to plausibly be a typo
+pub fn suggest(name: &str, candidates: &[String]) -> Option<String> {
+    let name = name.to_lowercase();
+    let threshold = name.chars().count().div_ceil(3).max(1);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+// Levenshtein distance between two strings, counted in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("major", "major"), 0);
+        assert_eq!(edit_distance("majr", "major"), 1);
+        assert_eq!(edit_distance("nmae", "name"), 2);
+        assert_eq!(edit_distance("", "id"), 2);
+    }
+
+    #[test]
+    fn test_suggest_closest_column() {
+        let columns = vec!["id".to_string(), "name".to_string(), "major".to_string()];
+        assert_eq!(suggest("mjor", &columns), Some("major".to_string()));
+        assert_eq!(suggest("NAME", &columns), Some("name".to_string()));
+        assert_eq!(suggest("age", &columns), None);
+    }
+}
@@ -0,0 +1,125 @@
+use std::cmp::Ordering;
+
+use sqlparser::ast::OrderByExpr;
+
+use crate::error::QueryError;
+use crate::value::Value;
+
+// How one ORDER BY key sorts. NULLs come last when ascending and first when descending, unless the
+// query says otherwise with NULLS FIRST/LAST.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl SortOrder {
+    pub fn from_ast(order: &OrderByExpr) -> Self {
+        let descending = order.asc == Some(false);
+        SortOrder { descending, nulls_first: order.nulls_first.unwrap_or(descending) }
+    }
+}
+
+// Compare two rows by their sort keys, one key at a time, using typed comparison for the values
+pub fn compare_keys(a: &[Value], b: &[Value], orders: &[SortOrder]) -> Result<Ordering, QueryError> {
+    for ((a, b), order) in a.iter().zip(b).zip(orders) {
+        let nulls = if order.nulls_first { Ordering::Less } else { Ordering::Greater };
+        let ordering = match (a, b) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => nulls,
+            (_, Value::Null) => nulls.reverse(),
+            _ => {
+                let ordering = a.compare(b)?.unwrap_or(Ordering::Equal);
+                if order.descending { ordering.reverse() } else { ordering }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return Ok(ordering);
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+// Sort items by their precomputed keys. The sort is stable, so rows with equal keys keep their order.
+pub fn sort_by_keys<T>(mut items: Vec<(Vec<Value>, T)>, orders: &[SortOrder]) -> Result<Vec<(Vec<Value>, T)>, QueryError> {
+    check_comparable(&items, orders)?;
+    let mut error = None;
+    items.sort_by(|(a, _), (b, _)| {
+        compare_keys(a, b, orders).unwrap_or_else(|err| {
+            error.get_or_insert(err);
+            Ordering::Equal
+        })
+    });
+    match error {
+        Some(err) => Err(err),
+        None => Ok(items),
+    }
+}
+
+// The sort only compares some pairs of keys, and which ones depends on the order the rows come in, so
+// check up front that every key can be compared with every other key of its column. Whether a value
+// compares with another only depends on the other's type (text coerces to it or not), so it's enough to
+// compare each value with the first value of every other type in the column.
+fn check_comparable<T>(items: &[(Vec<Value>, T)], orders: &[SortOrder]) -> Result<(), QueryError> {
+    for column in 0..orders.len() {
+        let mut firsts: Vec<&Value> = Vec::new();
+        for (key, _) in items {
+            let Some(value) = key.get(column).filter(|value| **value != Value::Null) else { continue };
+            let mut known = false;
+            for first in &firsts {
+                if first.data_type() == value.data_type() {
+                    known = true;
+                } else {
+                    value.compare(first)?;
+                }
+            }
+            if !known {
+                firsts.push(value);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASC: SortOrder = SortOrder { descending: false, nulls_first: false };
+    const DESC: SortOrder = SortOrder { descending: true, nulls_first: true };
+
+    fn sorted(keys: Vec<Vec<Value>>, orders: &[SortOrder]) -> Vec<usize> {
+        let items = keys.into_iter().enumerate().map(|(i, key)| (key, i)).collect();
+        sort_by_keys(items, orders).unwrap().into_iter().map(|(_, i)| i).collect()
+    }
+
+    #[test]
+    fn test_sort_directions_and_nulls() {
+        let keys = vec![vec![Value::Integer(10)], vec![Value::Null], vec![Value::Float(2.5)], vec![Value::Integer(10)]];
+        assert_eq!(sorted(keys.clone(), &[ASC]), vec![2, 0, 3, 1]);
+        assert_eq!(sorted(keys.clone(), &[DESC]), vec![1, 0, 3, 2]);
+        assert_eq!(sorted(keys, &[SortOrder { descending: false, nulls_first: true }]), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn test_later_keys_break_ties() {
+        let keys = vec![
+            vec![Value::from("CS"), Value::from("Charlie")],
+            vec![Value::from("Math"), Value::from("Bob")],
+            vec![Value::from("CS"), Value::from("Alice")],
+        ];
+        assert_eq!(sorted(keys.clone(), &[ASC, ASC]), vec![2, 0, 1]);
+        assert_eq!(sorted(keys, &[DESC, ASC]), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_keys_that_do_not_compare() {
+        // The sort itself might never compare 1 with 'a', since both are compared with the NULL
+        let items = vec![(vec![Value::Integer(1)], 0), (vec![Value::Null], 1), (vec![Value::from("a")], 2)];
+        assert!(matches!(sort_by_keys(items, &[ASC]), Err(QueryError::TypeMismatch { .. })));
+        let keys = vec![vec![Value::Integer(1)], vec![Value::Null], vec![Value::from("0.5")], vec![Value::from("b")]];
+        let items = keys.into_iter().enumerate().map(|(i, key)| (key, i)).collect();
+        assert!(matches!(sort_by_keys(items, &[ASC]), Err(QueryError::TypeMismatch { .. })));
+        assert_eq!(sorted(vec![vec![Value::Integer(1)], vec![Value::from("0.5")]], &[ASC]), vec![1, 0]);
+    }
+}
@@ -0,0 +1,490 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use sqlparser::ast;
+
+use crate::error::QueryError;
+
+// A calendar date, stored so that the derived ordering is chronological
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    // Parse an ISO 'YYYY-MM-DD' date, rejecting days that do not exist (e.g. 2023-02-29)
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        let date = Date { year, month, day };
+        if (1..=12).contains(&month) && day >= 1 && day <= date.days_in_month() {
+            Some(date)
+        } else {
+            None
+        }
+    }
+
+    fn days_in_month(&self) -> u32 {
+        match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// A date with a time of day (no time zone), ordered chronologically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub date: Date,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl Timestamp {
+    // Parse 'YYYY-MM-DD HH:MM[:SS]' (a 'T' separator is accepted too)
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let (date, time) = text.trim().split_once([' ', 'T'])?;
+        let date = Date::parse(date)?;
+        let mut parts = time.trim().splitn(3, ':');
+        let hour = parts.next()?.parse().ok()?;
+        let minute = parts.next()?.parse().ok()?;
+        let second = parts.next().map_or(Some(0), |s| s.parse().ok())?;
+        if hour < 24 && minute < 60 && second < 60 {
+            Some(Timestamp { date, hour, minute, second })
+        } else {
+            None
+        }
+    }
+
+    fn midnight(date: Date) -> Timestamp {
+        Timestamp { date, hour: 0, minute: 0, second: 0 }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:02}:{:02}:{:02}", self.date, self.hour, self.minute, self.second)
+    }
+}
+
+// The declared type of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataType {
+    Boolean,
+    Integer,
+    Float,
+    Text,
+    Date,
+    Timestamp,
+}
+
+impl DataType {
+    // Convert a value into this type where SQL allows it implicitly (e.g. 1 into a FLOAT column,
+    // '2024-01-31' into a DATE column); NULL fits every type
+    pub fn coerce(&self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (_, Value::Null) => Some(Value::Null),
+            (DataType::Boolean, Value::Bool(_))
+            | (DataType::Integer, Value::Integer(_))
+            | (DataType::Float, Value::Float(_))
+            | (DataType::Text, Value::Text(_))
+            | (DataType::Date, Value::Date(_))
+            | (DataType::Timestamp, Value::Timestamp(_)) => Some(value.clone()),
+            (DataType::Float, Value::Integer(i)) => Some(Value::Float(*i as f64)),
+            (DataType::Timestamp, Value::Date(d)) => Some(Value::Timestamp(Timestamp::midnight(*d))),
+            (DataType::Boolean, Value::Text(text)) => match text.trim().to_lowercase().as_str() {
+                "true" | "t" | "yes" | "1" => Some(Value::Bool(true)),
+                "false" | "f" | "no" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (DataType::Integer, Value::Text(text)) => text.trim().parse().ok().map(Value::Integer),
+            (DataType::Float, Value::Text(text)) => text.trim().parse().ok().map(Value::Float),
+            (DataType::Date, Value::Text(text)) => Date::parse(text).map(Value::Date),
+            (DataType::Timestamp, Value::Text(text)) => Timestamp::parse(text)
+                .or_else(|| Date::parse(text).map(Timestamp::midnight))
+                .map(Value::Timestamp),
+            _ => None,
+        }
+    }
+
+    // The type a SQL type name stands for, e.g. VARCHAR(20) is TEXT and DOUBLE PRECISION is FLOAT;
+    // None for types the validator has no values for
+    pub fn from_sql(data_type: &ast::DataType) -> Option<DataType> {
+        use ast::DataType as Sql;
+        match data_type {
+            Sql::Boolean => Some(DataType::Boolean),
+            Sql::TinyInt(_) | Sql::SmallInt(_) | Sql::MediumInt(_) | Sql::Int(_) | Sql::Integer(_) | Sql::BigInt(_) => {
+                Some(DataType::Integer)
+            }
+            Sql::Float(_)
+            | Sql::Real
+            | Sql::Double
+            | Sql::DoublePrecision
+            | Sql::Numeric(_)
+            | Sql::Decimal(_)
+            | Sql::Dec(_) => Some(DataType::Float),
+            Sql::Text
+            | Sql::String
+            | Sql::Char(_)
+            | Sql::Character(_)
+            | Sql::Varchar(_)
+            | Sql::CharVarying(_)
+            | Sql::CharacterVarying(_)
+            | Sql::Nvarchar(_) => Some(DataType::Text),
+            Sql::Date => Some(DataType::Date),
+            Sql::Timestamp(..) | Sql::Datetime(_) => Some(DataType::Timestamp),
+            _ => None,
+        }
+    }
+
+    // Whether values of the two types can be compared with each other without an explicit cast
+    pub fn comparable_with(&self, other: &DataType) -> bool {
+        let numeric = |t: &DataType| matches!(t, DataType::Integer | DataType::Float);
+        let temporal = |t: &DataType| matches!(t, DataType::Date | DataType::Timestamp);
+        self == other || (numeric(self) && numeric(other)) || (temporal(self) && temporal(other))
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataType::Boolean => "BOOLEAN",
+            DataType::Integer => "INTEGER",
+            DataType::Float => "FLOAT",
+            DataType::Text => "TEXT",
+            DataType::Date => "DATE",
+            DataType::Timestamp => "TIMESTAMP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A single typed cell of a row or result of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Date(Date),
+    Timestamp(Timestamp),
+}
+
+impl Value {
+    // Convert a literal from the parsed SQL into a value
+    pub fn from_literal(literal: &ast::Value) -> Result<Value, QueryError> {
+        match literal {
+            ast::Value::Number(n, _) => match n.parse::<i64>() {
+                Ok(i) => Ok(Value::Integer(i)),
+                Err(_) => n
+                    .parse::<f64>()
+                    .map(Value::Float)
+                    .map_err(|_| QueryError::UnsupportedExpression(literal.to_string())),
+            },
+            ast::Value::SingleQuotedString(s)
+            | ast::Value::DoubleQuotedString(s)
+            | ast::Value::EscapedStringLiteral(s)
+            | ast::Value::NationalStringLiteral(s) => Ok(Value::Text(s.clone())),
+            ast::Value::Boolean(b) => Ok(Value::Bool(*b)),
+            ast::Value::Null => Ok(Value::Null),
+            other => Err(QueryError::UnsupportedExpression(other.to_string())),
+        }
+    }
+
+    // The type of the value; NULL has no type of its own
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            Value::Null => None,
+            Value::Bool(_) => Some(DataType::Boolean),
+            Value::Integer(_) => Some(DataType::Integer),
+            Value::Float(_) => Some(DataType::Float),
+            Value::Text(_) => Some(DataType::Text),
+            Value::Date(_) => Some(DataType::Date),
+            Value::Timestamp(_) => Some(DataType::Timestamp),
+        }
+    }
+
+    // Compare two values using SQL coercion rules:
+    // - integers and floats compare numerically, and exactly even where the integer has no float of its own,
+    // - dates are promoted to timestamps at midnight,
+    // - text is coerced to the other side's type when it parses as one (so `id = '1'` works like `id = 1`).
+    // Returns Ok(None) when either side is NULL, and an error when the types cannot be compared.
+    pub fn compare(&self, other: &Value) -> Result<Option<Ordering>, QueryError> {
+        let ordering = match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => return Ok(None),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (Value::Timestamp(a), Value::Timestamp(b)) => a.cmp(b),
+            (Value::Date(a), Value::Timestamp(b)) => Timestamp::midnight(*a).cmp(b),
+            (Value::Timestamp(a), Value::Date(b)) => a.cmp(&Timestamp::midnight(*b)),
+            (Value::Float(a), Value::Float(b)) => match a.partial_cmp(b) {
+                Some(ordering) => ordering,
+                None => return Ok(None),
+            },
+            (Value::Integer(a), Value::Float(b)) => match Self::compare_integer_float(*a, *b) {
+                Some(ordering) => ordering,
+                None => return Ok(None),
+            },
+            (Value::Float(a), Value::Integer(b)) => match Self::compare_integer_float(*b, *a) {
+                Some(ordering) => ordering.reverse(),
+                None => return Ok(None),
+            },
+            (Value::Text(text), typed) => return Self::coerce_text(text, typed)?.compare(typed),
+            (typed, Value::Text(text)) => return typed.compare(&Self::coerce_text(text, typed)?),
+            (a, b) => return Err(Self::mismatch(a, b)),
+        };
+        Ok(Some(ordering))
+    }
+
+    // Converting a large integer to a float can round it (2^53 + 1 becomes 2^53), so a whole float an i64
+    // can hold is compared as an integer instead. Floats with a fraction are below 2^52, where any rounding
+    // of the integer cannot cross them, and None is for NaN.
+    fn compare_integer_float(integer: i64, float: f64) -> Option<Ordering> {
+        // i64::MAX as f64 is 2^63, just past the largest i64
+        if float >= i64::MAX as f64 {
+            Some(Ordering::Less)
+        } else if float < i64::MIN as f64 {
+            Some(Ordering::Greater)
+        } else if float.fract() == 0.0 {
+            Some(integer.cmp(&(float as i64)))
+        } else {
+            (integer as f64).partial_cmp(&float)
+        }
+    }
+
+    // An explicit CAST, which allows more than the implicit coercions: anything can become text, floats
+    // round to integers, integers and booleans convert both ways and timestamps drop their time of day
+    pub fn cast(&self, target: DataType) -> Result<Value, QueryError> {
+        let invalid = || QueryError::InvalidCast { from: self.describe(), to: target.to_string() };
+        match (self, target) {
+            (Value::Null, _) => Ok(Value::Null),
+            (Value::Text(text), _) => target
+                .coerce(self)
+                .ok_or_else(|| QueryError::InvalidLiteral { value: text.clone(), data_type: target.to_string() }),
+            (_, DataType::Text) => Ok(Value::Text(self.to_string())),
+            (Value::Float(x), DataType::Integer) => {
+                let rounded = x.round();
+                if rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+                    Ok(Value::Integer(rounded as i64))
+                } else {
+                    Err(QueryError::NumericOverflow { expr: None })
+                }
+            }
+            (Value::Integer(i), DataType::Boolean) => Ok(Value::Bool(*i != 0)),
+            (Value::Bool(b), DataType::Integer) => Ok(Value::Integer(i64::from(*b))),
+            (Value::Timestamp(timestamp), DataType::Date) => Ok(Value::Date(timestamp.date)),
+            _ => target.coerce(self).ok_or_else(invalid),
+        }
+    }
+
+    // SQL's IS DISTINCT FROM: like `<>`, except that NULL is not distinct from NULL but is from anything else
+    pub fn is_distinct_from(&self, other: &Value) -> Result<bool, QueryError> {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ok(false),
+            (Value::Null, _) | (_, Value::Null) => Ok(true),
+            _ => Ok(self.compare(other)? != Some(Ordering::Equal)),
+        }
+    }
+
+    // Interpret text as a value of the same type as `target`
+    fn coerce_text(text: &str, target: &Value) -> Result<Value, QueryError> {
+        let text = Value::Text(text.to_string());
+        let coerced = match target {
+            // Text compared with a number may hold either an integer or a float
+            Value::Integer(_) | Value::Float(_) => {
+                DataType::Integer.coerce(&text).or_else(|| DataType::Float.coerce(&text))
+            }
+            other => other.data_type().and_then(|t| t.coerce(&text)),
+        };
+        coerced.ok_or_else(|| Self::mismatch(&text, target))
+    }
+
+    // The numeric value as a float, for integers and floats only
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    fn mismatch(left: &Value, right: &Value) -> QueryError {
+        QueryError::TypeMismatch { left: left.describe(), right: right.describe(), expr: None }
+    }
+
+    // The value with its type, e.g. TEXT 'Alice', for error messages
+    pub fn describe(&self) -> String {
+        match (self, self.data_type()) {
+            (Value::Text(s), _) => format!("TEXT '{}'", s),
+            (other, Some(data_type)) => format!("{} {}", data_type, other),
+            (other, None) => other.to_string(),
+        }
+    }
+
+    // The value as a key for grouping rows and dropping duplicates with a hash map
+    pub fn key(&self) -> HashKey {
+        match self {
+            Value::Null => HashKey::Null,
+            Value::Bool(b) => HashKey::Bool(*b),
+            Value::Integer(i) => HashKey::Integer(*i),
+            Value::Float(x) if x.fract() == 0.0 && *x >= i64::MIN as f64 && *x < i64::MAX as f64 => {
+                HashKey::Integer(*x as i64)
+            }
+            Value::Float(x) if x.is_nan() => HashKey::Float(f64::NAN.to_bits()),
+            Value::Float(x) => HashKey::Float(x.to_bits()),
+            Value::Text(s) => HashKey::Text(s.clone()),
+            Value::Date(d) => HashKey::Date(*d),
+            Value::Timestamp(t) => HashKey::Timestamp(*t),
+        }
+    }
+}
+
+// A value that can be hashed. Numbers that compare equal have the same key, so 1 and 1.0 (and 0.0 and
+// -0.0) group together, and so does every NaN, the way NULLs do. Text is not coerced the way compare
+// does, so '1' and 1 have different keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    // The bits of a float that is not a whole number an i64 can hold
+    Float(u64),
+    Text(String),
+    Date(Date),
+    Timestamp(Timestamp),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Date(d) => write!(f, "{}", d),
+            Value::Timestamp(t) => write!(f, "{}", t),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Text(s.to_string())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Integer(i)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Float(x)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_compare_across_integer_and_float() {
+        assert_eq!(Value::Integer(2).compare(&Value::Float(10.5)), Ok(Some(Ordering::Less)));
+        assert_eq!(Value::Integer(10).compare(&Value::Integer(9)), Ok(Some(Ordering::Greater)));
+        // 2^53 + 1 is not equal to the float 2^53, though converting it to a float gives 2^53
+        let above = Value::Integer(9007199254740993);
+        assert_eq!(above.compare(&Value::Float(9007199254740992.0)), Ok(Some(Ordering::Greater)));
+        assert_eq!(Value::Float(9007199254740992.0).compare(&above), Ok(Some(Ordering::Less)));
+        assert_eq!(Value::Integer(i64::MAX).compare(&Value::Float(i64::MAX as f64)), Ok(Some(Ordering::Less)));
+        assert_eq!(Value::Integer(i64::MIN).compare(&Value::Float(i64::MIN as f64)), Ok(Some(Ordering::Equal)));
+        assert_eq!(Value::Integer(-3).compare(&Value::Float(-2.5)), Ok(Some(Ordering::Less)));
+        assert_eq!(Value::Integer(1).compare(&Value::Float(f64::NAN)), Ok(None));
+    }
+
+    #[test]
+    fn test_text_is_coerced_to_the_other_type() {
+        assert_eq!(Value::Integer(1).compare(&Value::from("1")), Ok(Some(Ordering::Equal)));
+        let date = Value::Date(Date::parse("2024-03-01").unwrap());
+        assert_eq!(date.compare(&Value::from("2024-02-29")), Ok(Some(Ordering::Greater)));
+        assert!(matches!(Value::Integer(1).compare(&Value::from("one")), Err(QueryError::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_data_type_coercion() {
+        assert_eq!(DataType::Float.coerce(&Value::Integer(2)), Some(Value::Float(2.0)));
+        assert_eq!(DataType::Integer.coerce(&Value::from("42")), Some(Value::Integer(42)));
+        assert_eq!(DataType::Text.coerce(&Value::Integer(42)), None);
+        assert!(DataType::Date.comparable_with(&DataType::Timestamp));
+        assert!(!DataType::Text.comparable_with(&DataType::Integer));
+    }
+
+    #[test]
+    fn test_is_distinct_from() {
+        assert_eq!(Value::Null.is_distinct_from(&Value::Null), Ok(false));
+        assert_eq!(Value::Null.is_distinct_from(&Value::Integer(1)), Ok(true));
+        assert_eq!(Value::Integer(1).is_distinct_from(&Value::Float(1.0)), Ok(false));
+        assert_eq!(Value::from("a").is_distinct_from(&Value::from("b")), Ok(true));
+    }
+
+    #[test]
+    fn test_cast() {
+        assert_eq!(Value::Float(2.5).cast(DataType::Integer), Ok(Value::Integer(3)));
+        assert_eq!(Value::Integer(42).cast(DataType::Text), Ok(Value::from("42")));
+        assert_eq!(Value::from(" 7 ").cast(DataType::Integer), Ok(Value::Integer(7)));
+        assert_eq!(Value::Integer(0).cast(DataType::Boolean), Ok(Value::Bool(false)));
+        let timestamp = Value::Timestamp(Timestamp::parse("2024-01-31 09:30").unwrap());
+        assert_eq!(timestamp.cast(DataType::Date), Ok(Value::Date(Date::parse("2024-01-31").unwrap())));
+        assert!(matches!(Value::from("abc").cast(DataType::Integer), Err(QueryError::InvalidLiteral { .. })));
+        assert!(matches!(Value::Bool(true).cast(DataType::Date), Err(QueryError::InvalidCast { .. })));
+        assert_eq!(Value::Float(1e20).cast(DataType::Integer), Err(QueryError::NumericOverflow { expr: None }));
+    }
+
+    #[test]
+    fn test_hash_keys() {
+        assert_eq!(Value::Integer(1).key(), Value::Float(1.0).key());
+        assert_eq!(Value::Float(0.0).key(), Value::Float(-0.0).key());
+        assert_eq!(Value::Float(f64::NAN).key(), Value::Float(-f64::NAN).key());
+        assert_ne!(Value::Float(1.5).key(), Value::Float(2.5).key());
+        assert_ne!(Value::Integer(9007199254740993).key(), Value::Float(9007199254740992.0).key());
+        assert_ne!(Value::Integer(1).key(), Value::from("1").key());
+        assert_ne!(Value::Null.key(), Value::Integer(0).key());
+    }
+
+    #[test]
+    fn test_null_is_incomparable() {
+        assert_eq!(Value::Null.compare(&Value::Integer(1)), Ok(None));
+    }
+
+    #[test]
+    fn test_date_parsing() {
+        assert!(Date::parse("2023-02-29").is_none());
+        assert!(Date::parse("2024-02-29").is_some());
+        let ts = Timestamp::parse("2024-01-02T03:04:05").unwrap();
+        assert_eq!(ts.to_string(), "2024-01-02 03:04:05");
+    }
+}
@@ -0,0 +1,577 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use sqlparser::ast::{
+    self, Expr, Function, FunctionArg, FunctionArgExpr, OrderByExpr, WindowFrameBound, WindowFrameUnits, WindowType,
+};
+
+use crate::aggregate::{Aggregate, AggregateFunction, Running};
+use crate::error::QueryError;
+use crate::functions::{invalid_argument, ArgType, FunctionRegistry, Returns, Signature};
+use crate::sort::{self, SortOrder};
+use crate::value::{DataType, HashKey, Value};
+
+// What a window call computes for each row
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowFunction<'a> {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    FirstValue,
+    // Any aggregate, computed over the frame of each row
+    Aggregate(Aggregate<'a>),
+}
+
+impl WindowFunction<'_> {
+    // One of the functions that can only be called with OVER; aggregates are window functions too,
+    // but are found with AggregateFunction
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "ROW_NUMBER" => Some(WindowFunction::RowNumber),
+            "RANK" => Some(WindowFunction::Rank),
+            "DENSE_RANK" => Some(WindowFunction::DenseRank),
+            "LAG" => Some(WindowFunction::Lag),
+            "LEAD" => Some(WindowFunction::Lead),
+            "FIRST_VALUE" => Some(WindowFunction::FirstValue),
+            _ => None,
+        }
+    }
+
+    // The arguments the function takes; aggregates check their own
+    pub fn signature(&self) -> Option<Signature> {
+        let integer = Returns::Type(DataType::Integer);
+        match self {
+            WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => {
+                Some(Signature::new(Vec::new(), integer))
+            }
+            // LAG(value, offset, default)
+            WindowFunction::Lag | WindowFunction::Lead => Some(
+                Signature::new(
+                    vec![ArgType::Any, ArgType::Type(DataType::Integer), ArgType::Any],
+                    Returns::FirstArgument,
+                )
+                .optional(2)
+                .called_on_null(),
+            ),
+            WindowFunction::FirstValue => {
+                Some(Signature::new(vec![ArgType::Any], Returns::FirstArgument).called_on_null())
+            }
+            WindowFunction::Aggregate(_) => None,
+        }
+    }
+}
+
+// One end of a window frame. Offsets are counted in rows for ROWS frames, and in ORDER BY key values for
+// RANGE frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(f64),
+    CurrentRow,
+    Following(f64),
+    UnboundedFollowing,
+}
+
+impl FrameBound {
+    // Bounds in the order they come in a partition, for checking that a frame does not end before it starts
+    fn position(&self) -> u8 {
+        match self {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::Preceding(_) => 1,
+            FrameBound::CurrentRow => 2,
+            FrameBound::Following(_) => 3,
+            FrameBound::UnboundedFollowing => 4,
+        }
+    }
+}
+
+// The rows of its partition a window function looks at for each row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub units: WindowFrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+// A call with OVER, e.g. `RANK() OVER (PARTITION BY major ORDER BY score DESC)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowCall<'a> {
+    pub name: String,
+    pub function: WindowFunction<'a>,
+    pub args: Vec<&'a Expr>,
+    pub partition_by: &'a [Expr],
+    pub order_by: &'a [OrderByExpr],
+    pub frame: Frame,
+}
+
+// Whether the expression is a window function call
+pub fn is_window(expr: &Expr) -> bool {
+    matches!(expr, Expr::Function(function) if function.over.is_some())
+}
+
+impl<'a> WindowCall<'a> {
+    // Recognize a window call and check its arguments and frame; Ok(None) for a call without OVER
+    pub fn parse(function: &'a Function, functions: &FunctionRegistry) -> Result<Option<Self>, QueryError> {
+        let spec = match &function.over {
+            None => return Ok(None),
+            Some(WindowType::WindowSpec(spec)) => spec,
+            Some(WindowType::NamedWindow(_)) => return Err(QueryError::UnsupportedExpression(function.to_string())),
+        };
+        let name = function.name.to_string().to_uppercase();
+        let invalid =
+            |message: &str| QueryError::InvalidWindow { function: name.clone(), message: message.to_string() };
+
+        let window_function = match WindowFunction::from_name(&name) {
+            Some(window_function) => window_function,
+            None => match AggregateFunction::resolve(&name, functions) {
+                Some(aggregate) => WindowFunction::Aggregate(Aggregate::from_call(aggregate, function)?),
+                None if functions.get(&name).is_some() => {
+                    return Err(invalid("OVER can only be used with window and aggregate functions"));
+                }
+                None => return Err(QueryError::UnknownFunction(name)),
+            },
+        };
+        let args = match &window_function {
+            WindowFunction::Aggregate(aggregate) => aggregate.args.clone(),
+            _ => {
+                if function.distinct {
+                    return Err(invalid("DISTINCT is only allowed in aggregate functions"));
+                }
+                let mut args = Vec::new();
+                for arg in &function.args {
+                    match arg {
+                        FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => args.push(expr),
+                        other => return Err(invalid(&format!("unexpected argument {}", other))),
+                    }
+                }
+                args
+            }
+        };
+        if let Some(signature) = window_function.signature() {
+            signature.check_arity(&name, args.len())?;
+        }
+
+        // Without a frame, a row sees its partition up to its last peer when there is an ORDER BY,
+        // and the whole partition otherwise
+        let frame = match &spec.window_frame {
+            None if spec.order_by.is_empty() => Frame {
+                units: WindowFrameUnits::Range,
+                start: FrameBound::UnboundedPreceding,
+                end: FrameBound::UnboundedFollowing,
+            },
+            None => Frame {
+                units: WindowFrameUnits::Range,
+                start: FrameBound::UnboundedPreceding,
+                end: FrameBound::CurrentRow,
+            },
+            Some(frame) => {
+                if frame.units == WindowFrameUnits::Groups {
+                    return Err(invalid("GROUPS frames are not supported"));
+                }
+                let start = frame_bound(&frame.start_bound, frame.units, &invalid)?;
+                let end = match &frame.end_bound {
+                    Some(bound) => frame_bound(bound, frame.units, &invalid)?,
+                    None => FrameBound::CurrentRow,
+                };
+                if start == FrameBound::UnboundedFollowing || end == FrameBound::UnboundedPreceding {
+                    return Err(invalid("a frame cannot start at UNBOUNDED FOLLOWING or end at UNBOUNDED PRECEDING"));
+                }
+                if start.position() > end.position() {
+                    return Err(invalid("the frame ends before it starts"));
+                }
+                let offset = |bound: FrameBound| matches!(bound, FrameBound::Preceding(_) | FrameBound::Following(_));
+                if frame.units == WindowFrameUnits::Range && (offset(start) || offset(end)) && spec.order_by.len() != 1
+                {
+                    return Err(invalid("RANGE with an offset needs exactly one ORDER BY key"));
+                }
+                Frame { units: frame.units, start, end }
+            }
+        };
+
+        Ok(Some(WindowCall {
+            name,
+            function: window_function,
+            args,
+            partition_by: &spec.partition_by,
+            order_by: &spec.order_by,
+            frame,
+        }))
+    }
+
+    // Compute the function for every row, in the order the rows are given; `eval` evaluates an
+    // argument, PARTITION BY or ORDER BY expression against one row
+    pub fn evaluate<F>(&self, rows: &[&[Value]], mut eval: F) -> Result<Vec<Value>, QueryError>
+    where
+        F: FnMut(&Expr, &[Value]) -> Result<Value, QueryError>,
+    {
+        // Split the rows into partitions, keeping NULL keys together like GROUP BY does
+        let mut indexes: HashMap<Vec<HashKey>, usize> = HashMap::new();
+        let mut partitions: Vec<Vec<usize>> = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            let mut key = Vec::new();
+            for expr in self.partition_by {
+                key.push(eval(expr, row)?.key());
+            }
+            let partition = *indexes.entry(key).or_insert(partitions.len());
+            if partition == partitions.len() {
+                partitions.push(Vec::new());
+            }
+            partitions[partition].push(index);
+        }
+
+        let orders: Vec<SortOrder> = self.order_by.iter().map(SortOrder::from_ast).collect();
+        let mut results = vec![Value::Null; rows.len()];
+        for partition in partitions {
+            let mut items = Vec::new();
+            for index in partition {
+                let mut key = Vec::new();
+                for order in self.order_by {
+                    key.push(eval(&order.expr, rows[index])?);
+                }
+                items.push((key, index));
+            }
+            let sorted = sort::sort_by_keys(items, &orders)?;
+            let partition = Partition::new(&sorted, rows, &orders)?;
+            // Frames that all start at the partition's first row grow as the rows go by
+            let mut running = match &self.function {
+                WindowFunction::Aggregate(aggregate) if self.frame.start == FrameBound::UnboundedPreceding => {
+                    aggregate.running()
+                }
+                _ => None,
+            };
+            for position in 0..sorted.len() {
+                results[sorted[position].1] = self.value_at(position, &partition, &mut running, &mut eval)?;
+            }
+        }
+        Ok(results)
+    }
+
+    // The function's value for the row at `position` of its sorted partition
+    fn value_at<F>(
+        &self,
+        position: usize,
+        partition: &Partition,
+        running: &mut Option<Running>,
+        eval: &mut F,
+    ) -> Result<Value, QueryError>
+    where
+        F: FnMut(&Expr, &[Value]) -> Result<Value, QueryError>,
+    {
+        let row = partition.rows[position];
+        match &self.function {
+            WindowFunction::RowNumber => Ok(Value::Integer(position as i64 + 1)),
+            WindowFunction::Rank => Ok(Value::Integer(partition.peer_start[position] as i64 + 1)),
+            WindowFunction::DenseRank => Ok(Value::Integer(partition.peer_group[position] as i64 + 1)),
+            WindowFunction::Lag | WindowFunction::Lead => {
+                let offset = match self.args.get(1) {
+                    Some(expr) => eval(expr, row)?,
+                    None => Value::Integer(1),
+                };
+                let param = ArgType::Type(DataType::Integer);
+                let offset = match param.convert(&offset) {
+                    Some(Value::Integer(offset)) => offset,
+                    Some(_) => return Ok(Value::Null),
+                    None => return Err(invalid_argument(&self.name, 1, param, &offset.describe())),
+                };
+                let target = match self.function {
+                    WindowFunction::Lag => (position as i64).checked_sub(offset),
+                    _ => (position as i64).checked_add(offset),
+                };
+                match target.and_then(|target| usize::try_from(target).ok()).filter(|target| *target < partition.len())
+                {
+                    Some(target) => eval(self.args[0], partition.rows[target]),
+                    None => match self.args.get(2) {
+                        Some(default) => eval(default, row),
+                        None => Ok(Value::Null),
+                    },
+                }
+            }
+            WindowFunction::FirstValue => {
+                let frame = self.frame_at(position, partition)?;
+                match frame.is_empty() {
+                    true => Ok(Value::Null),
+                    false => eval(self.args[0], partition.rows[frame.start]),
+                }
+            }
+            WindowFunction::Aggregate(aggregate) => {
+                let frame = self.frame_at(position, partition)?;
+                match running {
+                    Some(running) if frame.start == 0 => running.evaluate(&partition.rows, frame.end, &mut *eval),
+                    _ => aggregate.evaluate(&partition.rows[frame], &mut *eval),
+                }
+            }
+        }
+    }
+
+    // The positions of the rows in the frame of the row at `position`
+    fn frame_at(&self, position: usize, partition: &Partition) -> Result<Range<usize>, QueryError> {
+        let len = partition.len();
+        let (start, end) = match self.frame.units {
+            WindowFrameUnits::Rows => {
+                let start = match self.frame.start {
+                    FrameBound::Preceding(n) => position.saturating_sub(n as usize),
+                    FrameBound::CurrentRow => position,
+                    FrameBound::Following(n) => position.saturating_add(n as usize),
+                    FrameBound::UnboundedPreceding | FrameBound::UnboundedFollowing => 0,
+                };
+                let end = match self.frame.end {
+                    FrameBound::Preceding(n) => position.checked_sub(n as usize).map_or(0, |last| last + 1),
+                    FrameBound::CurrentRow => position + 1,
+                    FrameBound::Following(n) => position.saturating_add(n as usize).saturating_add(1),
+                    FrameBound::UnboundedPreceding | FrameBound::UnboundedFollowing => len,
+                };
+                (start, end)
+            }
+            _ => {
+                let key = |i: usize| partition.keys[i][0].as_f64();
+                let start = match self.frame.start {
+                    FrameBound::CurrentRow => partition.peer_start[position],
+                    bound @ (FrameBound::Preceding(_) | FrameBound::Following(_)) => {
+                        match self.range_bound(position, bound, partition)? {
+                            // The first row whose key is not before the bound
+                            Some((bound, descending)) => (0..len)
+                                .find(|&i| key(i).is_some_and(|k| in_order(bound, k, descending)))
+                                .unwrap_or(len),
+                            None => partition.peer_start[position],
+                        }
+                    }
+                    FrameBound::UnboundedPreceding | FrameBound::UnboundedFollowing => 0,
+                };
+                let end = match self.frame.end {
+                    FrameBound::CurrentRow => partition.peer_end[position],
+                    bound @ (FrameBound::Preceding(_) | FrameBound::Following(_)) => {
+                        match self.range_bound(position, bound, partition)? {
+                            // Up to the last row whose key is not after the bound
+                            Some((bound, descending)) => (0..len)
+                                .rev()
+                                .find(|&i| key(i).is_some_and(|k| in_order(k, bound, descending)))
+                                .map_or(0, |last| last + 1),
+                            None => partition.peer_end[position],
+                        }
+                    }
+                    FrameBound::UnboundedPreceding | FrameBound::UnboundedFollowing => len,
+                };
+                (start, end)
+            }
+        };
+        let end = end.min(len);
+        Ok(start.min(end)..end)
+    }
+
+    // For a RANGE offset: the key value the bound falls on for the row at `position`, and whether the
+    // key sorts descending. None when the row's key is NULL, in which case the bound falls on its peers.
+    fn range_bound(
+        &self,
+        position: usize,
+        bound: FrameBound,
+        partition: &Partition,
+    ) -> Result<Option<(f64, bool)>, QueryError> {
+        for key in &partition.keys {
+            if key[0] != Value::Null && key[0].as_f64().is_none() {
+                return Err(QueryError::InvalidWindow {
+                    function: self.name.clone(),
+                    message: format!("RANGE with an offset needs a numeric ORDER BY key, found {}", key[0].describe()),
+                });
+            }
+        }
+        let current = match partition.keys[position][0].as_f64() {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+        let descending = self.order_by.first().is_some_and(|order| SortOrder::from_ast(order).descending);
+        // PRECEDING moves towards the start of the partition, which holds the larger keys when descending
+        Ok(Some(match (bound, descending) {
+            (FrameBound::Preceding(n), false) | (FrameBound::Following(n), true) => (current - n, descending),
+            (FrameBound::Preceding(n), true) | (FrameBound::Following(n), false) => (current + n, descending),
+            _ => (current, descending),
+        }))
+    }
+}
+
+// Whether `a` sorts no later than `b`
+fn in_order(a: f64, b: f64, descending: bool) -> bool {
+    if descending {
+        a >= b
+    } else {
+        a <= b
+    }
+}
+
+// One partition's rows in ORDER BY order, with the peers of each row: the rows that sort equal to it
+struct Partition<'r> {
+    rows: Vec<&'r [Value]>,
+    keys: Vec<Vec<Value>>,
+    // The first and one past the last position of each row's peers, and how many sets of peers come before
+    peer_start: Vec<usize>,
+    peer_end: Vec<usize>,
+    peer_group: Vec<usize>,
+}
+
+impl<'r> Partition<'r> {
+    fn new(sorted: &[(Vec<Value>, usize)], rows: &[&'r [Value]], orders: &[SortOrder]) -> Result<Self, QueryError> {
+        let len = sorted.len();
+        let mut peer_start = vec![0; len];
+        let mut peer_group = vec![0; len];
+        for position in 1..len {
+            if sort::compare_keys(&sorted[position].0, &sorted[position - 1].0, orders)? == Ordering::Equal {
+                peer_start[position] = peer_start[position - 1];
+                peer_group[position] = peer_group[position - 1];
+            } else {
+                peer_start[position] = position;
+                peer_group[position] = peer_group[position - 1] + 1;
+            }
+        }
+        let mut peer_end = vec![len; len];
+        for position in (0..len.saturating_sub(1)).rev() {
+            if peer_start[position + 1] == peer_start[position] {
+                peer_end[position] = peer_end[position + 1];
+            } else {
+                peer_end[position] = position + 1;
+            }
+        }
+        Ok(Partition {
+            rows: sorted.iter().map(|(_, index)| rows[*index]).collect(),
+            keys: sorted.iter().map(|(key, _)| key.clone()).collect(),
+            peer_start,
+            peer_end,
+            peer_group,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+// Read one end of a frame; offsets have to be non-negative number literals, and whole numbers for ROWS
+fn frame_bound(
+    bound: &WindowFrameBound,
+    units: WindowFrameUnits,
+    invalid: &dyn Fn(&str) -> QueryError,
+) -> Result<FrameBound, QueryError> {
+    let offset = |expr: &Expr| {
+        let offset = match expr {
+            Expr::Value(ast::Value::Number(n, _)) => n.parse::<f64>().ok(),
+            _ => None,
+        };
+        match offset {
+            Some(n) if n >= 0.0 && n.is_finite() && (units == WindowFrameUnits::Range || n.fract() == 0.0) => Ok(n),
+            _ => Err(invalid(&format!("frame offsets must be non-negative constants, found {}", expr))),
+        }
+    };
+    Ok(match bound {
+        WindowFrameBound::CurrentRow => FrameBound::CurrentRow,
+        WindowFrameBound::Preceding(None) => FrameBound::UnboundedPreceding,
+        WindowFrameBound::Preceding(Some(expr)) => FrameBound::Preceding(offset(expr)?),
+        WindowFrameBound::Following(None) => FrameBound::UnboundedFollowing,
+        WindowFrameBound::Following(Some(expr)) => FrameBound::Following(offset(expr)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    fn call(sql: &str) -> Function {
+        match Parser::new(&GenericDialect {}).try_with_sql(sql).unwrap().parse_expr().unwrap() {
+            Expr::Function(function) => function,
+            other => panic!("not a function call: {}", other),
+        }
+    }
+
+    // Run a window call over rows of (group, value), where `g` and `x` name the two columns
+    fn run(sql: &str, rows: &[(&str, Value)]) -> Result<Vec<Value>, QueryError> {
+        let function = call(sql);
+        let window = WindowCall::parse(&function, &FunctionRegistry::default())?.unwrap();
+        let rows: Vec<Vec<Value>> =
+            rows.iter().map(|(group, value)| vec![Value::from(*group), value.clone()]).collect();
+        let rows: Vec<&[Value]> = rows.iter().map(Vec::as_slice).collect();
+        window.evaluate(&rows, |expr, row| match expr.to_string().as_str() {
+            "g" => Ok(row[0].clone()),
+            "x" => Ok(row[1].clone()),
+            other => Ok(Value::Integer(other.parse().unwrap())),
+        })
+    }
+
+    fn integers(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|v| Value::Integer(*v)).collect()
+    }
+
+    #[test]
+    fn test_ranking() {
+        let rows =
+            [("a", Value::Integer(10)), ("b", Value::Integer(20)), ("a", Value::Integer(10)), ("a", Value::Integer(5))];
+        assert_eq!(run("ROW_NUMBER() OVER (ORDER BY x)", &rows), Ok(integers(&[2, 4, 3, 1])));
+        assert_eq!(run("RANK() OVER (ORDER BY x)", &rows), Ok(integers(&[2, 4, 2, 1])));
+        assert_eq!(run("DENSE_RANK() OVER (ORDER BY x)", &rows), Ok(integers(&[2, 3, 2, 1])));
+        assert_eq!(run("RANK() OVER (PARTITION BY g ORDER BY x DESC)", &rows), Ok(integers(&[1, 1, 1, 3])));
+        assert_eq!(run("RANK() OVER ()", &rows), Ok(integers(&[1, 1, 1, 1])));
+    }
+
+    #[test]
+    fn test_offsets_and_first_value() {
+        let rows =
+            [("a", Value::Integer(1)), ("a", Value::Integer(2)), ("b", Value::Integer(3)), ("a", Value::Integer(4))];
+        assert_eq!(
+            run("LAG(x) OVER (PARTITION BY g ORDER BY x)", &rows),
+            Ok(vec![Value::Null, Value::Integer(1), Value::Null, Value::Integer(2)])
+        );
+        assert_eq!(run("LEAD(x, 2, 0) OVER (ORDER BY x)", &rows), Ok(integers(&[3, 4, 0, 0])));
+        assert_eq!(run("FIRST_VALUE(x) OVER (PARTITION BY g ORDER BY x DESC)", &rows), Ok(integers(&[4, 4, 3, 4])));
+        assert_eq!(
+            run("FIRST_VALUE(x) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)", &rows),
+            Ok(integers(&[1, 1, 2, 3]))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_frames() {
+        let rows =
+            [("a", Value::Integer(1)), ("a", Value::Integer(2)), ("a", Value::Integer(2)), ("a", Value::Integer(5))];
+        // By default a row sees everything up to its last peer
+        assert_eq!(run("SUM(x) OVER (ORDER BY x)", &rows), Ok(integers(&[1, 5, 5, 10])));
+        assert_eq!(run("SUM(x) OVER (ORDER BY x ROWS UNBOUNDED PRECEDING)", &rows), Ok(integers(&[1, 3, 5, 10])));
+        assert_eq!(run("COUNT(*) OVER ()", &rows), Ok(integers(&[4, 4, 4, 4])));
+        assert_eq!(
+            run("SUM(x) OVER (ORDER BY x ROWS BETWEEN 1 FOLLOWING AND UNBOUNDED FOLLOWING)", &rows),
+            Ok(vec![Value::Integer(9), Value::Integer(7), Value::Integer(5), Value::Null])
+        );
+        assert_eq!(
+            run("SUM(x) OVER (ORDER BY x RANGE BETWEEN 1 PRECEDING AND 1 FOLLOWING)", &rows),
+            Ok(integers(&[5, 5, 5, 5]))
+        );
+        assert_eq!(
+            run("MAX(x) OVER (ORDER BY x DESC RANGE BETWEEN CURRENT ROW AND 3 FOLLOWING)", &rows),
+            Ok(integers(&[1, 2, 2, 5]))
+        );
+        assert_eq!(
+            run("COUNT(x) OVER (ORDER BY x RANGE BETWEEN 3 PRECEDING AND 1 PRECEDING)", &rows),
+            Ok(integers(&[0, 1, 1, 2]))
+        );
+    }
+
+    #[test]
+    fn test_invalid_window_calls() {
+        let parse =
+            |sql: &str| WindowCall::parse(&call(sql), &FunctionRegistry::default()).map(|window| window.is_some());
+        let invalid = |sql: &str| matches!(parse(sql), Err(QueryError::InvalidWindow { .. }));
+        assert_eq!(parse("UPPER(x)"), Ok(false));
+        assert!(invalid("UPPER(x) OVER ()"));
+        assert!(invalid("SUM(x) OVER (ORDER BY x GROUPS CURRENT ROW)"));
+        assert!(invalid("SUM(x) OVER (ROWS BETWEEN CURRENT ROW AND 1 PRECEDING)"));
+        assert!(invalid("SUM(x) OVER (ROWS BETWEEN UNBOUNDED FOLLOWING AND UNBOUNDED FOLLOWING)"));
+        assert!(invalid("SUM(x) OVER (ROWS 1.5 PRECEDING)"));
+        assert!(invalid("SUM(x) OVER (ORDER BY g, x RANGE 1 PRECEDING)"));
+        assert!(matches!(parse("RANK(x) OVER ()"), Err(QueryError::InvalidArguments { .. })));
+        assert!(matches!(parse("NTILE(4) OVER ()"), Err(QueryError::UnknownFunction(_))));
+        assert!(matches!(
+            run("SUM(x) OVER (ORDER BY g RANGE 1 PRECEDING)", &[("a", Value::Integer(1))]),
+            Err(QueryError::InvalidWindow { .. })
+        ));
+    }
+}